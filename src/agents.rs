@@ -0,0 +1,504 @@
+use std::collections::HashSet;
+
+use raylib::prelude::*;
+use rand::prelude::*;
+
+use crate::*;
+
+/// Agents move once per this many grid steps, about 20 times a second at the default speed.
+const AGENT_INTERVAL: i32 = ITERATIONS * 3;
+/// Agent steps a creature survives without eating.
+const MAX_HUNGER: i32 = 2400;
+/// Agent steps a fish survives out of water.
+const FISH_BREATH: i32 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Species {
+    Ant,
+    Fish,
+    Bird,
+}
+
+impl Species {
+    pub fn color(&self) -> Color {
+        match &self {
+            Self::Ant => Color{r: 90, g: 30, b: 20, a: 255},
+            Self::Fish => Color{r: 255, g: 140, b: 0, a: 255},
+            Self::Bird => Color{r: 110, g: 80, b: 210, a: 255},
+        }
+    }
+
+    /// Whether the creature eats cells of the given state.
+    pub fn eats(&self, state: &CellStates) -> bool {
+        match &self {
+            Self::Ant => matches!(state, CellStates::Seed | CellStates::Leaf),
+            Self::Fish => matches!(state, CellStates::Plague | CellStates::Seed | CellStates::Leaf),
+            Self::Bird => matches!(state, CellStates::Seed),
+        }
+    }
+}
+
+impl std::fmt::Display for Species {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self {
+            Self::Ant => write!(f, "Ant"),
+            Self::Fish => write!(f, "Fish"),
+            Self::Bird => write!(f, "Bird"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Behaviour {
+    /// Ant walking around, digging into sand it bumps into.
+    Wander,
+    /// Ant carrying a grain, with the steps left before dropping it.
+    Carry(i32),
+    Swim,
+    /// Fish out of water, with the steps it can still survive.
+    Flop(i32),
+    Fly,
+    /// Bird sitting on the ground, with the steps left to rest.
+    Rest(i32),
+    /// Bird flying away from fire, with the steps left to flee.
+    Flee(i32),
+}
+
+/// A creature living in the grid.
+///
+/// The grid holds a `CellStates::Creature` cell at `pos`, so that the rest of the simulation sees the creature.
+#[derive(Debug, Clone, Copy)]
+pub struct Agent {
+    pub species: Species,
+    pub pos: (i32, i32),
+    /// Direction of travel, across and down as seen with gravity pointing down.
+    pub heading: (i32, i32),
+    pub hunger: i32,
+    pub behaviour: Behaviour,
+    /// Grain carried by an ant.
+    pub carrying: Option<CellStates>,
+}
+
+impl Agent {
+    pub fn new(species: Species, pos: (i32, i32), rng: &mut StdRng) -> Agent {
+        let side = rng.gen_range(0..2) * 2 - 1;
+        let (heading, behaviour) = match species {
+            Species::Ant => ((side, 0), Behaviour::Wander),
+            Species::Fish => ((side, 0), Behaviour::Swim),
+            Species::Bird => ((side, -1), Behaviour::Fly),
+        };
+        Agent {
+            species,
+            pos,
+            heading,
+            hunger: 0,
+            behaviour,
+            carrying: None,
+        }
+    }
+
+    /// Does one step of the agent, returns false when it died.
+    fn step(&mut self, grid: &mut Grid) -> bool {
+        let deadly = grid.find_all_around_where(self.pos.0, self.pos.1, |state| matches!(state, CellStates::Fire(_) | CellStates::Burning | CellStates::Acid | CellStates::ToxicGas));
+        if !deadly.is_empty() {
+            self.die(grid);
+            return false;
+        }
+        self.hunger += 1;
+        if self.hunger > MAX_HUNGER / 2 {
+            let species = self.species;
+            let food = grid.find_all_around_where(self.pos.0, self.pos.1, |state| species.eats(state));
+            if let Some(food) = food.first() {
+                grid.set(food.0, food.1, CellStates::Air);
+                self.hunger = 0;
+            }
+        }
+        if self.hunger >= MAX_HUNGER {
+            self.die(grid);
+            return false;
+        }
+        match self.species {
+            Species::Ant => self.step_ant(grid),
+            Species::Fish => self.step_fish(grid),
+            Species::Bird => self.step_bird(grid),
+        }
+    }
+
+    fn die(&self, grid: &mut Grid) {
+        grid.set(self.pos.0, self.pos.1, CellStates::Ash);
+    }
+
+    fn state_at(grid: &Grid, pos: (i32, i32)) -> Option<CellStates> {
+        grid.get_cell(pos.0, pos.1).map(|cell| cell.state)
+    }
+
+    fn move_to(&mut self, grid: &mut Grid, target: (i32, i32)) {
+        grid.swap(self.pos.0, self.pos.1, target);
+        self.pos = target;
+    }
+
+    /// Position `local` steps across and down from the agent, as seen with gravity pointing down.
+    fn towards(&self, grid: &Grid, local: (i32, i32)) -> (i32, i32) {
+        let down = grid.down();
+        let across = (down.1, -down.0);
+        (
+            self.pos.0 + local.0 * across.0 + local.1 * down.0,
+            self.pos.1 + local.0 * across.1 + local.1 * down.1,
+        )
+    }
+
+    /// Lets the agent fall with gravity into a cell `open` allows, returns whether it fell.
+    fn fall(&mut self, grid: &mut Grid, open: impl Fn(&CellStates) -> bool) -> bool {
+        let below = match grid.fall(self.pos) {
+            Some(down) => (self.pos.0 + down.0, self.pos.1 + down.1),
+            None => return false,
+        };
+        match Self::state_at(grid, below) {
+            Some(state) if open(&state) => {
+                self.move_to(grid, below);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn step_ant(&mut self, grid: &mut Grid) -> bool {
+        // fall through anything softer than sand
+        if self.fall(grid, |state| state.hardness() > Hardness::Solid as i32) {
+            return true;
+        }
+        if grid.rng.gen_range(0..50) == 0 {
+            self.heading.0 *= -1;
+        }
+        let ahead = self.towards(grid, (self.heading.0, 0));
+        if let Behaviour::Carry(steps) = self.behaviour {
+            // drop the grain behind, or anywhere there is room when deep in a tunnel
+            let behind = self.towards(grid, (-self.heading.0, 0));
+            let drop = match Self::state_at(grid, behind) {
+                Some(CellStates::Air) => Some(behind),
+                _ => grid.find_all_around(self.pos.0, self.pos.1, &CellStates::Air).first().copied(),
+            };
+            match drop {
+                Some(drop) if steps <= 0 => {
+                    grid.set(drop.0, drop.1, self.carrying.take().unwrap_or(CellStates::Sand));
+                    self.behaviour = Behaviour::Wander;
+                }
+                _ => self.behaviour = Behaviour::Carry(steps - 1),
+            }
+        }
+        // sometimes dig down instead of straight ahead
+        let dig = match grid.rng.gen_range(0..3) {
+            0 => self.towards(grid, (self.heading.0, 1)),
+            _ => ahead,
+        };
+        match Self::state_at(grid, dig) {
+            Some(state @ (CellStates::Sand | CellStates::WetSand | CellStates::Soil)) if self.behaviour == Behaviour::Wander => {
+                if grid.rng.gen_range(0..4) == 0 {
+                    grid.set(dig.0, dig.1, CellStates::Air);
+                    self.carrying = Some(state);
+                    self.behaviour = Behaviour::Carry(grid.rng.gen_range(20..60));
+                    self.move_to(grid, dig);
+                }
+                return true;
+            }
+            _ => (),
+        }
+        match Self::state_at(grid, ahead) {
+            Some(CellStates::Air) => self.move_to(grid, ahead),
+            Some(_) => {
+                // climb a single step, otherwise turn around
+                let up = self.towards(grid, (self.heading.0, -1));
+                if Self::state_at(grid, up) == Some(CellStates::Air)
+                    && Self::state_at(grid, self.towards(grid, (0, -1))) == Some(CellStates::Air)
+                {
+                    self.move_to(grid, up);
+                } else {
+                    self.heading.0 *= -1;
+                }
+            }
+            None => self.heading.0 *= -1,
+        }
+        true
+    }
+
+    fn step_fish(&mut self, grid: &mut Grid) -> bool {
+        if !grid.is_around(self.pos.0, self.pos.1, &CellStates::Water) {
+            let breath = match self.behaviour {
+                Behaviour::Flop(breath) => breath - 1,
+                _ => FISH_BREATH,
+            };
+            if breath <= 0 {
+                self.die(grid);
+                return false;
+            }
+            self.behaviour = Behaviour::Flop(breath);
+            if self.fall(grid, |state| *state == CellStates::Air) {
+                return true;
+            }
+            let side = grid.rng.gen_range(-1..2);
+            let hop = self.towards(grid, (side, 0));
+            if Self::state_at(grid, hop) == Some(CellStates::Air) {
+                self.move_to(grid, hop);
+            }
+            return true;
+        }
+        self.behaviour = Behaviour::Swim;
+        if grid.rng.gen_range(0..20) == 0 {
+            self.heading = (grid.rng.gen_range(0..2) * 2 - 1, grid.rng.gen_range(-1..2));
+        }
+        let target = self.towards(grid, self.heading);
+        match Self::state_at(grid, target) {
+            Some(CellStates::Water) => self.move_to(grid, target),
+            _ => self.heading = (-self.heading.0, grid.rng.gen_range(-1..2)),
+        }
+        true
+    }
+
+    fn step_bird(&mut self, grid: &mut Grid) -> bool {
+        let fire = (-2..3)
+            .flat_map(|i| (-2..3).map(move |j| (i, j)))
+            .find(|local| matches!(Self::state_at(grid, self.towards(grid, *local)), Some(CellStates::Fire(_) | CellStates::Burning)));
+        if let Some((i, _)) = fire {
+            self.heading = (if i > 0 { -1 } else { 1 }, -1);
+            self.behaviour = Behaviour::Flee(30);
+        }
+        match self.behaviour {
+            Behaviour::Rest(steps) => {
+                let below = Self::state_at(grid, self.towards(grid, (0, 1)));
+                if steps <= 0 || below == Some(CellStates::Air) {
+                    self.heading = (self.heading.0, -1);
+                    self.behaviour = Behaviour::Fly;
+                } else {
+                    self.behaviour = Behaviour::Rest(steps - 1);
+                }
+                return true;
+            }
+            Behaviour::Flee(steps) => {
+                self.behaviour = match steps {
+                    0 => Behaviour::Fly,
+                    _ => Behaviour::Flee(steps - 1),
+                };
+            }
+            _ => {
+                if grid.rng.gen_range(0..10) == 0 {
+                    self.heading.1 = grid.rng.gen_range(-1..2);
+                }
+            }
+        }
+        let target = self.towards(grid, self.heading);
+        match Self::state_at(grid, target) {
+            Some(CellStates::Air) => self.move_to(grid, target),
+            _ => {
+                let below = Self::state_at(grid, self.towards(grid, (0, 1)));
+                if self.behaviour == Behaviour::Fly && below.is_some() && below != Some(CellStates::Air) && grid.rng.gen_range(0..3) == 0 {
+                    self.behaviour = Behaviour::Rest(grid.rng.gen_range(100..300));
+                } else {
+                    self.heading = (-self.heading.0, grid.rng.gen_range(-1..2));
+                }
+            }
+        }
+        true
+    }
+}
+
+/// All creatures living in the grid.
+#[derive(Debug, Clone)]
+pub struct Agents {
+    pub list: Vec<Agent>,
+    /// Grid steps done since the last agent step.
+    budget: i32,
+}
+
+impl Agents {
+    pub fn new() -> Agents {
+        Agents {
+            list: Vec::new(),
+            budget: 0,
+        }
+    }
+
+    /// Advances the agents by the number of steps the grid just did.
+    pub fn update(&mut self, grid: &mut Grid, steps: i32) {
+        self.budget += steps;
+        while self.budget >= AGENT_INTERVAL {
+            self.budget -= AGENT_INTERVAL;
+            self.step(grid);
+        }
+    }
+
+    pub fn step(&mut self, grid: &mut Grid) {
+        self.sync(grid);
+        self.list.retain_mut(|agent| agent.step(grid));
+    }
+
+    /// Forgets agents whose cell was overwritten and adopts creature cells without an agent.
+    ///
+    /// This is how painted creatures come alive, and how saves and undo (which only keep cells) stay consistent.
+    fn sync(&mut self, grid: &mut Grid) {
+        self.list.retain(|agent| Agent::state_at(grid, agent.pos) == Some(CellStates::Creature(agent.species)));
+        let taken: HashSet<(i32, i32)> = self.list.iter().map(|agent| agent.pos).collect();
+        for x in 0..grid.size.0 {
+            for y in 0..grid.size.1 {
+                if let CellStates::Creature(species) = grid.cells[x as usize][y as usize].state {
+                    if !taken.contains(&(x, y)) {
+                        let agent = Agent::new(species, (x, y), &mut grid.rng);
+                        self.list.push(agent);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the agent living in the given cell.
+    pub fn at(&self, pos: (i32, i32)) -> Option<&Agent> {
+        self.list.iter().find(|agent| agent.pos == pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Grid with a fixed seed and a wall along the bottom row.
+    fn ground(size: (i32, i32)) -> Grid {
+        let mut grid = Grid::new(size);
+        grid.seed(7);
+        for x in 0..size.0 {
+            grid.set(x, size.1 - 1, CellStates::Wall);
+        }
+        grid
+    }
+
+    fn spawn(grid: &mut Grid, species: Species, pos: (i32, i32)) -> Agent {
+        grid.set(pos.0, pos.1, CellStates::Creature(species));
+        Agent::new(species, pos, &mut grid.rng)
+    }
+
+    #[test]
+    fn ant_falls_and_wanders() {
+        let mut grid = ground((10, 6));
+        let mut ant = spawn(&mut grid, Species::Ant, (5, 0));
+        for _ in 0..4 {
+            assert!(ant.step(&mut grid));
+        }
+        assert_eq!(ant.pos, (5, 4));
+        for _ in 0..10 {
+            assert!(ant.step(&mut grid));
+        }
+        assert_eq!(ant.pos.1, 4);
+        assert_ne!(ant.pos.0, 5);
+        assert_eq!(ant.behaviour, Behaviour::Wander);
+        assert_eq!(grid.cells[ant.pos.0 as usize][4].state, CellStates::Creature(Species::Ant));
+    }
+
+    #[test]
+    fn ants_fall_with_gravity() {
+        let mut grid = Grid::new((5, 5));
+        grid.seed(7);
+        grid.gravity = Some(Dir::Up);
+        let mut ant = spawn(&mut grid, Species::Ant, (2, 3));
+        ant.step(&mut grid);
+        assert_eq!(ant.pos, (2, 2));
+    }
+
+    #[test]
+    fn ant_carries_sand_away() {
+        let mut grid = ground((12, 6));
+        for x in 6..12 {
+            for y in 2..5 {
+                grid.set(x, y, CellStates::Sand);
+            }
+        }
+        let sand = |grid: &Grid| grid.cells.iter().flatten().filter(|cell| cell.state == CellStates::Sand).count();
+        let before = sand(&grid);
+        let mut ant = spawn(&mut grid, Species::Ant, (5, 4));
+        ant.heading = (1, 0);
+        let mut carried = false;
+        for _ in 0..400 {
+            ant.step(&mut grid);
+            if let Behaviour::Carry(_) = ant.behaviour {
+                carried = true;
+                assert_eq!(ant.carrying, Some(CellStates::Sand));
+            } else if carried {
+                break;
+            }
+        }
+        assert!(carried);
+        assert_eq!(ant.behaviour, Behaviour::Wander);
+        assert_eq!(sand(&grid), before);
+    }
+
+    #[test]
+    fn fish_swims_in_water() {
+        let mut grid = Grid::new((10, 5));
+        grid.seed(7);
+        for x in 0..10 {
+            for y in 0..5 {
+                grid.set(x, y, CellStates::Water);
+            }
+        }
+        let mut fish = spawn(&mut grid, Species::Fish, (5, 2));
+        let mut moved = false;
+        for _ in 0..20 {
+            assert!(fish.step(&mut grid));
+            moved |= fish.pos != (5, 2);
+        }
+        assert!(moved);
+        assert_eq!(fish.behaviour, Behaviour::Swim);
+        assert_eq!(grid.cells.iter().flatten().filter(|cell| cell.state == CellStates::Water).count(), 49);
+    }
+
+    #[test]
+    fn fish_out_of_water_flops_and_dies() {
+        let mut grid = ground((5, 3));
+        let mut fish = spawn(&mut grid, Species::Fish, (2, 1));
+        assert!(fish.step(&mut grid));
+        assert_eq!(fish.behaviour, Behaviour::Flop(FISH_BREATH));
+        for _ in 1..FISH_BREATH {
+            assert!(fish.step(&mut grid));
+        }
+        assert!(!fish.step(&mut grid));
+        assert_eq!(grid.cells[fish.pos.0 as usize][fish.pos.1 as usize].state, CellStates::Ash);
+    }
+
+    #[test]
+    fn bird_flies() {
+        let mut grid = Grid::new((20, 20));
+        grid.seed(7);
+        let mut bird = spawn(&mut grid, Species::Bird, (10, 10));
+        bird.step(&mut grid);
+        assert_ne!(bird.pos, (10, 10));
+        assert_eq!(bird.behaviour, Behaviour::Fly);
+    }
+
+    #[test]
+    fn bird_rests_when_blocked_on_the_ground() {
+        let mut grid = Grid::new((3, 3));
+        grid.seed(7);
+        for x in 0..3 {
+            for y in 0..3 {
+                grid.set(x, y, CellStates::Wall);
+            }
+        }
+        let mut bird = spawn(&mut grid, Species::Bird, (1, 1));
+        for _ in 0..20 {
+            bird.step(&mut grid);
+            if let Behaviour::Rest(_) = bird.behaviour {
+                break;
+            }
+        }
+        assert!(matches!(bird.behaviour, Behaviour::Rest(_)), "{:?}", bird.behaviour);
+        assert_eq!(bird.pos, (1, 1));
+    }
+
+    #[test]
+    fn bird_flees_fire() {
+        let mut grid = Grid::new((20, 20));
+        grid.seed(7);
+        grid.set(12, 10, CellStates::Fire(2));
+        let mut bird = spawn(&mut grid, Species::Bird, (10, 10));
+        assert!(bird.step(&mut grid));
+        assert!(matches!(bird.behaviour, Behaviour::Flee(_)), "{:?}", bird.behaviour);
+        assert_eq!(bird.pos, (9, 9));
+    }
+}
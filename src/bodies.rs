@@ -0,0 +1,290 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::*;
+
+/// Bodies move once per this many grid steps, about 60 times a second at the default speed.
+const BODY_INTERVAL: i32 = ITERATIONS;
+/// Most cells a body falls in one of its steps.
+const MAX_SPEED: i32 = 3;
+
+/// Group of cells moving and turning as one object, painted as crates or boulders.
+#[derive(Debug, Clone)]
+pub struct Body {
+    pub material: CellStates,
+    /// Id from `Grid::body_id`, kept in `CellData::counter` of all its cells.
+    pub id: i32,
+    pub cells: Vec<(i32, i32)>,
+    /// Cells fallen in the last step, fast bodies plough into powders.
+    pub speed: i32,
+}
+
+impl Body {
+    /// Number of cells still holding the body.
+    fn intact(&self, grid: &Grid) -> usize {
+        self.cells
+            .iter()
+            .filter(|pos| matches!(grid.get_cell(pos.0, pos.1), Some(cell) if cell.state == self.material && cell.data.counter == self.id))
+            .count()
+    }
+
+    fn step(&mut self, grid: &mut Grid) {
+        let gravity = grid.gravity;
+        let down = match gravity {
+            Some(dir) if grid.pulled() => dir.offset(),
+            _ => return,
+        };
+        let density = self.material.density();
+        let denser = move |state: &CellStates| state.fluid() && state.density() > density;
+        // rows of the body across gravity, a body floats with as many of them in the liquid as its density asks for
+        let up = (-down.0, -down.1);
+        let across = (down.1, -down.0);
+        let level = |pos: &(i32, i32)| pos.0 * down.0 + pos.1 * down.1;
+        let members: HashSet<(i32, i32)> = self.cells.iter().copied().collect();
+        let rows: HashSet<i32> = self.cells.iter().map(level).collect();
+        let wet: HashSet<i32> = self
+            .cells
+            .iter()
+            .filter(|pos| {
+                [up, across, (-across.0, -across.1)].iter().any(|offset| {
+                    let next = (pos.0 + offset.0, pos.1 + offset.1);
+                    !members.contains(&next) && matches!(grid.get_cell(next.0, next.1), Some(cell) if denser(&cell.state))
+                })
+            })
+            .map(level)
+            .collect();
+        let afloat = (rows.len() as i32 * density + 50) / 100;
+        if wet.len() as i32 > afloat {
+            self.speed = 0;
+            self.shift(grid, up, |state| state.hardness() > Hardness::Solid as i32 && (!state.fluid() || denser(state)));
+            return;
+        }
+        let sinking = (wet.len() as i32) < afloat;
+        let open = move |state: &CellStates| {
+            state.hardness() > Hardness::Solid as i32 && (!state.fluid() || state.density() < density || (sinking && denser(state)))
+        };
+        let mut speed = self.speed;
+        let mut moved = 0;
+        let mut ploughed = false;
+        for _ in 0..(self.speed + 1).min(MAX_SPEED) {
+            if self.shift(grid, down, open) {
+                moved += 1;
+                continue;
+            }
+            // a fast body sinks into the powder it lands in, slowing down
+            if speed >= 2 && self.shift(grid, down, |state| open(state) || state.loose()) {
+                speed -= 1;
+                moved += 1;
+                ploughed = true;
+                continue;
+            }
+            break;
+        }
+        self.speed = if ploughed { speed } else { moved };
+        if moved == 0 {
+            self.tip(grid, down, open);
+        }
+    }
+
+    /// Moves the whole body by `offset` when every cell in the way is passable, pushing those cells behind it.
+    fn shift(&mut self, grid: &mut Grid, offset: (i32, i32), passable: impl Fn(&CellStates) -> bool) -> bool {
+        let members: HashSet<(i32, i32)> = self.cells.iter().copied().collect();
+        let free = self.cells.iter().all(|pos| {
+            let target = (pos.0 + offset.0, pos.1 + offset.1);
+            members.contains(&target) || matches!(grid.get_cell(target.0, target.1), Some(cell) if passable(&cell.state))
+        });
+        if !free {
+            return false;
+        }
+        // the front goes first and makes room for the cells behind it
+        self.cells.sort_by_key(|pos| -(pos.0 * offset.0 + pos.1 * offset.1));
+        for pos in self.cells.iter_mut() {
+            let target = (pos.0 + offset.0, pos.1 + offset.1);
+            grid.swap(pos.0, pos.1, target);
+            *pos = target;
+        }
+        true
+    }
+
+    /// Tips the body over the edge of its support when its centre is past that edge.
+    fn tip(&mut self, grid: &mut Grid, down: (i32, i32), open: impl Fn(&CellStates) -> bool) {
+        let members: HashSet<(i32, i32)> = self.cells.iter().copied().collect();
+        // sideways and down as seen with gravity pointing down
+        let across = (down.1, -down.0);
+        let local = |pos: (i32, i32)| (pos.0 * across.0 + pos.1 * across.1, pos.0 * down.0 + pos.1 * down.1);
+        let supports: Vec<(i32, i32)> = self
+            .cells
+            .iter()
+            .copied()
+            .filter(|pos| {
+                let below = (pos.0 + down.0, pos.1 + down.1);
+                !members.contains(&below) && grid.get_cell(below.0, below.1).is_none_or(|cell| !open(&cell.state))
+            })
+            .collect();
+        let (first, last) = match (supports.iter().min_by_key(|pos| local(**pos).0), supports.iter().max_by_key(|pos| local(**pos).0)) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return,
+        };
+        let centre = self.cells.iter().map(|pos| local(*pos).0).sum::<i32>() as f32 / self.cells.len() as f32;
+        let (pivot, side) = if centre > local(last).0 as f32 {
+            (last, 1)
+        } else if centre < local(first).0 as f32 {
+            (first, -1)
+        } else {
+            return;
+        };
+        // quarter turn around the outer lower corner of the pivot, towards the overhanging side
+        let turned: Vec<(i32, i32)> = self
+            .cells
+            .iter()
+            .map(|pos| {
+                let (u, v) = (local(*pos).0 - local(pivot).0, local(*pos).1 - local(pivot).1);
+                let (u, v) = if side == 1 { (1 - v, u) } else { (v - 1, -u) };
+                (pivot.0 + u * across.0 + v * down.0, pivot.1 + u * across.1 + v * down.1)
+            })
+            .collect();
+        let fits = turned.iter().all(|pos| {
+            members.contains(pos) || matches!(grid.get_cell(pos.0, pos.1), Some(cell) if cell.state.hardness() > Hardness::Solid as i32)
+        });
+        if !fits {
+            return;
+        }
+        let targets: HashSet<(i32, i32)> = turned.iter().copied().collect();
+        let body: Vec<Cell> = self.cells.iter().map(|pos| grid.cells[pos.0 as usize][pos.1 as usize]).collect();
+        let displaced: Vec<Cell> = turned
+            .iter()
+            .filter(|pos| !members.contains(pos))
+            .map(|pos| grid.cells[pos.0 as usize][pos.1 as usize])
+            .collect();
+        let vacated: Vec<(i32, i32)> = self.cells.iter().copied().filter(|pos| !targets.contains(pos)).collect();
+        for (pos, cell) in turned.iter().zip(body) {
+            grid.cells[pos.0 as usize][pos.1 as usize] = cell;
+        }
+        for (pos, cell) in vacated.into_iter().zip(displaced) {
+            grid.cells[pos.0 as usize][pos.1 as usize] = cell;
+        }
+        self.cells = turned;
+    }
+
+    /// Breaks what is left of the body into loose cells.
+    fn shatter(&self, grid: &mut Grid) {
+        let rubble = self.material.rubble().unwrap_or(CellStates::Air);
+        for pos in self.cells.iter() {
+            if matches!(grid.get_cell(pos.0, pos.1), Some(cell) if cell.state == self.material && cell.data.counter == self.id) {
+                grid.set(pos.0, pos.1, rubble);
+            }
+        }
+    }
+}
+
+impl Grid {
+    /// Hands out a new id for a rigid body. Body cells only join cells with the same id.
+    pub fn body_id(&mut self) -> i32 {
+        self.body_ids += 1;
+        self.body_ids
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Bodies {
+    pub list: Vec<Body>,
+    /// Grid steps not yet used for a body step.
+    budget: i32,
+}
+
+impl Bodies {
+    pub fn new() -> Bodies {
+        Bodies {
+            list: Vec::new(),
+            budget: 0,
+        }
+    }
+
+    /// Advances the bodies by the number of steps the grid just did.
+    pub fn update(&mut self, grid: &mut Grid, steps: i32) {
+        self.budget += steps;
+        while self.budget >= BODY_INTERVAL {
+            self.budget -= BODY_INTERVAL;
+            self.step(grid);
+        }
+    }
+
+    pub fn step(&mut self, grid: &mut Grid) {
+        self.sync(grid);
+        for body in self.list.iter_mut() {
+            body.step(grid);
+        }
+    }
+
+    /// Forgets all bodies, for when the whole grid is replaced. The cells are adopted again on the next step.
+    pub fn forget(&mut self) {
+        self.list.clear();
+    }
+
+    /// Breaks up bodies that lost some of their cells and adopts body cells without a body.
+    ///
+    /// Connected cells with the same id become one body, so saves and undo (which only keep cells) stay consistent.
+    /// Ids of adopted cells are never handed out again.
+    /// Cells added to a body that is still being painted merge into it.
+    fn sync(&mut self, grid: &mut Grid) {
+        let mut broken = Vec::new();
+        self.list.retain(|body| {
+            let intact = body.intact(grid);
+            if intact == body.cells.len() {
+                return true;
+            }
+            // a body with no cells left was cleared away rather than broken
+            if intact > 0 {
+                broken.push(body.clone());
+            }
+            false
+        });
+        for body in broken {
+            body.shatter(grid);
+        }
+        let owner: HashMap<(i32, i32), usize> = self
+            .list
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, body)| body.cells.iter().map(move |pos| (*pos, idx)))
+            .collect();
+        let mut seen = HashSet::new();
+        let mut merged = HashSet::new();
+        let mut adopted = Vec::new();
+        for x in 0..grid.size.0 {
+            for y in 0..grid.size.1 {
+                let cell = grid.cells[x as usize][y as usize];
+                if cell.state.rubble().is_none() || owner.contains_key(&(x, y)) || seen.contains(&(x, y)) {
+                    continue;
+                }
+                grid.body_ids = grid.body_ids.max(cell.data.counter);
+                let mut cells = Vec::new();
+                let mut stack = vec![(x, y)];
+                seen.insert((x, y));
+                while let Some(pos) = stack.pop() {
+                    cells.push(pos);
+                    if let Some(idx) = owner.get(&pos) {
+                        merged.insert(*idx);
+                    }
+                    for next in [(pos.0, pos.1 - 1), (pos.0 + 1, pos.1), (pos.0, pos.1 + 1), (pos.0 - 1, pos.1)] {
+                        let same = matches!(grid.get_cell(next.0, next.1), Some(other) if other.state == cell.state && other.data.counter == cell.data.counter);
+                        if same && seen.insert(next) {
+                            stack.push(next);
+                        }
+                    }
+                }
+                adopted.push(Body {
+                    material: cell.state,
+                    id: cell.data.counter,
+                    cells,
+                    speed: 0,
+                });
+            }
+        }
+        let mut idx = 0;
+        self.list.retain(|_| {
+            idx += 1;
+            !merged.contains(&(idx - 1))
+        });
+        self.list.extend(adopted);
+    }
+}
@@ -1,5 +1,12 @@
 use raylib::prelude::*;
 
+use crate::agents::Species;
+
+/// Length of a freshly placed vine.
+pub const VINE_LENGTH: u8 = 40;
+/// Material of emitters placed without one, like the ones in scenes.
+pub const EMITTER_MATERIAL: CellStates = CellStates::Water;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum CellStates {
     Air,
@@ -16,6 +23,63 @@ pub enum CellStates {
     Acid,
     Wood,
     Ash,
+    Metal,
+    Power,
+    Diode(Dir),
+    And(Dir),
+    Or(Dir),
+    Not(Dir),
+    Delay(Dir),
+    Switch(bool),
+    Lamp(bool),
+    Seed,
+    Soil,
+    /// Growing tip of a plant, with the height left to grow.
+    Stem(u8),
+    Leaf,
+    /// Vine with the length left to grow, only the tip has more than zero.
+    Vine(u8),
+    Creature(Species),
+    Lava,
+    Stone,
+    Obsidian,
+    Glass,
+    Ice,
+    Snow,
+    Cryo,
+    Oil,
+    Fuel,
+    Methane,
+    Smoke,
+    /// Emits the material stored in its cell.
+    Emitter,
+    Drain,
+    /// Emits the material of the first cell that moves or is placed next to it.
+    Clone,
+    BlackHole,
+    Repulsor,
+    Fan(Dir),
+    Slime,
+    Honey,
+    Mud,
+    WetSand,
+    /// Part of a rigid body, see `Bodies`.
+    Crate,
+    /// Part of a rigid body, see `Bodies`.
+    Boulder,
+    Sawdust,
+    Gravel,
+    /// Material on fire, the material and the fuel left are kept in its cell data, see `Cell::matter`.
+    Burning,
+    Charcoal,
+    Base,
+    Salt,
+    ToxicGas,
+    /// Can catch the plague, see `Contagion`.
+    Host,
+    /// Recovered from the plague, for a while.
+    Immune,
+    Cure,
 }
 
 impl CellStates {
@@ -33,18 +97,82 @@ impl CellStates {
                 0 => Color{r: 200, g: 255, b: 0, a: 255},
                 1 => Color{r: 200, g: 100, b: 0, a: 255},
                 2 => Color{r: 200, g: 0, b: 0, a: 255},
-                3 => Color{r: 70, g: 194, b: 203, a: 255},
-                _ => unreachable!("Fire not handled properly by the game")
+                // anything hotter burns blue as well
+                _ => Color{r: 70, g: 194, b: 203, a: 255},
             }
             Self::Gunpowder => Color{r: 51, g: 51, b: 51, a: 255},
             Self::Spark => Color::WHITE,
             Self::Acid => Color{r: 0, g: 223, b: 70, a: 255},
             Self::Wood => Color{r: 153, g: 102, b: 51, a: 255},
             Self::Ash => Color{r: 95, g: 95, b: 95, a: 255},
+            Self::Metal => Color{r: 140, g: 145, b: 160, a: 255},
+            Self::Power => Color{r: 230, g: 180, b: 0, a: 255},
+            Self::Diode(_) => Color{r: 120, g: 90, b: 160, a: 255},
+            Self::And(_) => Color{r: 60, g: 160, b: 140, a: 255},
+            Self::Or(_) => Color{r: 160, g: 140, b: 60, a: 255},
+            Self::Not(_) => Color{r: 170, g: 70, b: 90, a: 255},
+            Self::Delay(_) => Color{r: 90, g: 110, b: 190, a: 255},
+            Self::Switch(on) => match *on {
+                true => Color{r: 100, g: 220, b: 100, a: 255},
+                false => Color{r: 40, g: 90, b: 40, a: 255},
+            }
+            Self::Lamp(lit) => match *lit {
+                true => Color{r: 255, g: 250, b: 200, a: 255},
+                false => Color{r: 90, g: 85, b: 60, a: 255},
+            }
+            Self::Seed => Color{r: 190, g: 150, b: 80, a: 255},
+            Self::Soil => Color{r: 110, g: 70, b: 40, a: 255},
+            Self::Stem(_) => Color{r: 70, g: 160, b: 40, a: 255},
+            Self::Leaf => Color{r: 40, g: 210, b: 70, a: 255},
+            Self::Vine(_) => Color{r: 30, g: 130, b: 60, a: 255},
+            Self::Creature(species) => species.color(),
+            Self::Lava => Color{r: 255, g: 90, b: 0, a: 255},
+            Self::Stone => Color{r: 125, g: 125, b: 130, a: 255},
+            Self::Obsidian => Color{r: 45, g: 25, b: 60, a: 255},
+            Self::Glass => Color{r: 200, g: 230, b: 240, a: 255},
+            Self::Ice => Color{r: 170, g: 220, b: 255, a: 255},
+            Self::Snow => Color{r: 240, g: 245, b: 255, a: 255},
+            Self::Cryo => Color{r: 120, g: 255, b: 255, a: 255},
+            Self::Oil => Color{r: 70, g: 45, b: 25, a: 255},
+            Self::Fuel => Color{r: 230, g: 200, b: 130, a: 255},
+            Self::Methane => Color{r: 150, g: 190, b: 140, a: 255},
+            Self::Smoke => Color{r: 75, g: 75, b: 80, a: 255},
+            Self::Emitter => Color{r: 150, g: 150, b: 210, a: 255},
+            Self::Drain => Color{r: 70, g: 0, b: 90, a: 255},
+            Self::Clone => Color{r: 220, g: 180, b: 40, a: 255},
+            Self::BlackHole => Color{r: 60, g: 20, b: 110, a: 255},
+            Self::Repulsor => Color{r: 255, g: 120, b: 200, a: 255},
+            Self::Fan(_) => Color{r: 160, g: 200, b: 200, a: 255},
+            Self::Slime => Color{r: 120, g: 220, b: 80, a: 255},
+            Self::Honey => Color{r: 235, g: 170, b: 30, a: 255},
+            Self::Mud => Color{r: 95, g: 65, b: 40, a: 255},
+            Self::WetSand => Color{r: 185, g: 165, b: 40, a: 255},
+            Self::Crate => Color{r: 170, g: 120, b: 60, a: 255},
+            Self::Boulder => Color{r: 110, g: 105, b: 100, a: 255},
+            Self::Sawdust => Color{r: 215, g: 185, b: 125, a: 255},
+            Self::Gravel => Color{r: 140, g: 135, b: 130, a: 255},
+            Self::Burning => Self::glow(1),
+            Self::Charcoal => Color{r: 45, g: 40, b: 40, a: 255},
+            Self::Base => Color{r: 120, g: 120, b: 230, a: 255},
+            Self::Salt => Color{r: 235, g: 235, b: 230, a: 255},
+            Self::ToxicGas => Color{r: 170, g: 200, b: 40, a: 255},
+            Self::Host => Color{r: 230, g: 190, b: 150, a: 255},
+            Self::Immune => Color{r: 120, g: 180, b: 255, a: 255},
+            Self::Cure => Color{r: 250, g: 190, b: 220, a: 255},
             Self::Border => unreachable!("Border should not be drawn"),
         }
     }
 
+    /// Glowing version of the flame colour of the given level, for burning cells.
+    pub fn glow(heat: u8) -> Color {
+        match heat {
+            0 => Color{r: 150, g: 190, b: 0, a: 255},
+            1 => Color{r: 220, g: 90, b: 20, a: 255},
+            2 => Color{r: 170, g: 20, b: 10, a: 255},
+            _ => Color{r: 60, g: 150, b: 170, a: 255},
+        }
+    }
+
     pub fn hardness(&self) -> i32 {
         match &self {
             Self::Air => Hardness::Nothing as i32,
@@ -61,36 +189,375 @@ impl CellStates {
             Self::Acid => Hardness::Liquid as i32,
             Self::Wood => Hardness::Solid as i32,
             Self::Ash => Hardness::Solid as i32,
+            Self::Metal => Hardness::Solid as i32,
+            Self::Power => Hardness::Solid as i32,
+            Self::Diode(_) => Hardness::Solid as i32,
+            Self::And(_) => Hardness::Solid as i32,
+            Self::Or(_) => Hardness::Solid as i32,
+            Self::Not(_) => Hardness::Solid as i32,
+            Self::Delay(_) => Hardness::Solid as i32,
+            Self::Switch(_) => Hardness::Solid as i32,
+            Self::Lamp(_) => Hardness::Solid as i32,
+            Self::Seed => Hardness::Solid as i32,
+            Self::Soil => Hardness::Solid as i32,
+            Self::Stem(_) => Hardness::Solid as i32,
+            Self::Leaf => Hardness::Solid as i32,
+            Self::Vine(_) => Hardness::Solid as i32,
+            Self::Creature(_) => Hardness::Solid as i32,
+            Self::Lava => Hardness::Liquid as i32,
+            Self::Stone => Hardness::Solid as i32,
+            Self::Obsidian => Hardness::Solid as i32,
+            Self::Glass => Hardness::Solid as i32,
+            Self::Ice => Hardness::Solid as i32,
+            Self::Snow => Hardness::Solid as i32,
+            Self::Cryo => Hardness::Liquid as i32,
+            Self::Oil => Hardness::Liquid as i32,
+            Self::Fuel => Hardness::Liquid as i32,
+            Self::Methane => Hardness::Gas as i32,
+            Self::Smoke => Hardness::Gas as i32,
+            Self::Emitter => Hardness::Solid as i32,
+            Self::Drain => Hardness::Solid as i32,
+            Self::Clone => Hardness::Solid as i32,
+            Self::BlackHole => Hardness::Solid as i32,
+            Self::Repulsor => Hardness::Solid as i32,
+            Self::Fan(_) => Hardness::Solid as i32,
+            Self::Slime => Hardness::Slime as i32,
+            Self::Honey => Hardness::Slime as i32,
+            Self::Mud => Hardness::Slime as i32,
+            Self::WetSand => Hardness::Solid as i32,
+            Self::Crate => Hardness::Solid as i32,
+            Self::Boulder => Hardness::Solid as i32,
+            Self::Sawdust => Hardness::Solid as i32,
+            Self::Gravel => Hardness::Solid as i32,
+            Self::Burning => Hardness::Solid as i32,
+            Self::Charcoal => Hardness::Solid as i32,
+            Self::Base => Hardness::Liquid as i32,
+            Self::Salt => Hardness::Solid as i32,
+            Self::ToxicGas => Hardness::Gas as i32,
+            Self::Host => Hardness::Solid as i32,
+            Self::Immune => Hardness::Solid as i32,
+            Self::Cure => Hardness::Liquid as i32,
         }
     }
 
     pub fn weight(&self) -> i32 {
         match &self {
             Self::Water => 5,
-            Self::Acid => 6,
+            Self::Acid | Self::Base => 6,
+            Self::Lava => 20,
+            Self::Cryo => 4,
+            Self::Oil => 8,
+            Self::Fuel => 3,
+            Self::Slime => 6,
+            Self::Honey => 10,
+            Self::Mud => 8,
+            _ => 1,
+        }
+    }
+
+    /// How far a structural solid reaches out from its support before it breaks off, `None` for the rest.
+    pub fn strength(&self) -> Option<i32> {
+        match &self {
+            Self::Glass => Some(3),
+            Self::Ice => Some(4),
+            Self::Stone => Some(6),
+            Self::Wall => Some(8),
+            Self::Obsidian => Some(10),
+            Self::Wood => Some(12),
+            _ => None,
+        }
+    }
+
+    /// How deep a powder must be able to drop diagonally before it slides, piles get steeper the higher it is.
+    ///
+    /// Zero makes it roll off sideways as well, so it piles flat.
+    pub fn repose(&self) -> i32 {
+        match &self {
+            Self::Gunpowder => 0,
+            Self::Ash | Self::Snow | Self::Sawdust => 2,
+            Self::WetSand => 3,
+            _ => 1,
+        }
+    }
+
+    /// How thick a fluid is. Thick fluids move only once in `viscosity - 1` steps, lava in one of three,
+    /// spread sideways only once in `viscosity` of those and cling to walls.
+    pub fn viscosity(&self) -> i32 {
+        match &self {
+            Self::Oil => 2,
+            Self::Lava => 4,
+            Self::Slime => 6,
+            Self::Mud => 8,
+            Self::Honey => 10,
             _ => 1,
         }
     }
 
+    /// Liquids sink through other liquids with a lower density, so lighter ones float on top.
+    ///
+    /// Rigid bodies float on liquids denser than them.
+    pub fn density(&self) -> i32 {
+        match &self {
+            Self::Acid => 120,
+            Self::Base => 110,
+            Self::Lava => 300,
+            Self::Cryo => 80,
+            Self::Oil => 90,
+            Self::Fuel => 80,
+            Self::Slime => 105,
+            Self::Honey => 140,
+            Self::Mud => 160,
+            Self::Crate => 60,
+            Self::Boulder => 250,
+            _ => 100,
+        }
+    }
+
+    /// Whether the fluid sinks when `other` is under it.
+    pub fn sinks_into(&self, other: &CellStates) -> bool {
+        self.fluid() && self.hardness() == other.hardness() && self.density() > other.density()
+    }
+
+    /// Whether the cell flows, thick or not.
+    pub fn fluid(&self) -> bool {
+        self.hardness() == Hardness::Liquid as i32 || self.hardness() == Hardness::Slime as i32
+    }
+
+    /// How many tries acid needs on average to eat the solid, `None` for the ones it can't.
+    pub fn resistance(&self) -> Option<i32> {
+        match &self {
+            Self::Glass | Self::Barrier | Self::Salt => None,
+            Self::Metal => Some(20),
+            Self::Obsidian => Some(16),
+            Self::Stone => Some(8),
+            Self::Wood | Self::Crate | Self::Plague => Some(1),
+            _ => Some(2),
+        }
+    }
+
+    /// Whether acid eating the cell gives off toxic gas.
+    pub fn toxic(&self) -> bool {
+        matches!(self, Self::Metal | Self::Gunpowder | Self::Plague | Self::Creature(_))
+    }
+
+    /// How the material burns, `None` for the ones that don't.
+    pub fn burn(&self) -> Option<Burn> {
+        let (ignition, fuel, heat, residue) = match &self {
+            Self::Wood => (20, 320, 1, Self::Charcoal),
+            Self::Crate => (20, 240, 1, Self::Charcoal),
+            Self::Charcoal => (3, 640, 2, Self::Ash),
+            Self::Sawdust => (30, 30, 1, Self::Ash),
+            Self::Seed | Self::Stem(_) | Self::Leaf | Self::Vine(_) => (50, 40, 1, Self::Ash),
+            Self::Plague => (100, 8, 0, Self::Air),
+            Self::Gunpowder => (100, 2, 3, Self::Smoke),
+            Self::Oil => (100, 480, 3, Self::Smoke),
+            Self::Fuel => (100, 60, 2, Self::Air),
+            _ => return None,
+        };
+        Some(Burn { ignition, fuel, heat, residue })
+    }
+
+    /// Loose cells a rigid body breaks into, `None` for cells that are not part of one.
+    pub fn rubble(&self) -> Option<CellStates> {
+        match &self {
+            Self::Crate => Some(Self::Sawdust),
+            Self::Boulder => Some(Self::Gravel),
+            _ => None,
+        }
+    }
+
+    /// How much the brightness of single cells differs, so that materials don't look flat.
+    pub fn variation(&self) -> i32 {
+        match &self {
+            Self::Lava => 60,
+            Self::Stone | Self::Boulder => 14,
+            Self::Gravel => 12,
+            Self::Burning => 30,
+            Self::Charcoal => 8,
+            Self::Salt => 6,
+            Self::Crate => 8,
+            Self::Obsidian => 8,
+            Self::Ice => 6,
+            Self::Sand | Self::WetSand | Self::Soil | Self::Wood | Self::Sawdust => 10,
+            _ => 0,
+        }
+    }
+
     pub fn temperature(&self) -> i32 {
         match &self {
+            Self::Burning => 1000,
             Self::Fire(level) => match *level {
                 0 => 600,
                 1 => 1000,
                 2 => 1500,
-                3 => 2200,
-                _ => unreachable!("Fire not handled properly by the game")
+                _ => 2200,
             }
             Self::Vapor => 100,
             Self::Spark => 1000,
             Self::Ash => 100,
+            Self::Lava => 1200,
+            Self::Ice => -10,
+            Self::Snow => -5,
+            Self::Cryo => -190,
             _ => 0,
         }
     }
 
-    /// Returns a list of all possible cell states in order. (except border)
+    /// Short description of the behaviour, shown in the help window.
+    pub fn description(&self) -> &'static str {
+        match &self {
+            Self::Air => "Empty space",
+            Self::Sand => "Powder, piles up and sinks in liquids",
+            Self::Water => "Liquid, boils into vapor near fire",
+            Self::Plague => "Infects hosts, then dies or recovers",
+            Self::Wall => "Static solid, dissolved by acid",
+            Self::Barrier => "Indestructible solid",
+            Self::Fire(_) => "Rises and dies out, ignites things",
+            Self::Gunpowder => "Powder, burns fast, heaps explode",
+            Self::Spark => "Explosion spreading fire",
+            Self::Vapor => "Gas, rises and condenses into water",
+            Self::Acid => "Liquid, dissolves solids, weakens in water",
+            Self::Wood => "Static solid, burns long into charcoal",
+            Self::Ash => "Powder left after burning",
+            Self::Metal => "Wire, conducts electricity",
+            Self::Power => "Powers the wires it touches",
+            Self::Diode(_) => "Passes charge only forward",
+            Self::And(_) => "Fires when all inputs are on",
+            Self::Or(_) => "Fires when any input is on",
+            Self::Not(_) => "Fires when no input is on",
+            Self::Delay(_) => "Passes charge forward later",
+            Self::Switch(_) => "Conducts when on, click to flip",
+            Self::Lamp(_) => "Lights up when powered",
+            Self::Seed => "Sprouts on sand or soil near water",
+            Self::Soil => "Powder, plants grow in it",
+            Self::Stem(_) => "Growing plant, drinks water",
+            Self::Leaf => "Top of a plant, burns easily",
+            Self::Vine(_) => "Climbs walls and wood",
+            Self::Creature(Species::Ant) => "Digs tunnels, carries grains",
+            Self::Creature(Species::Fish) => "Swims in water, dies outside",
+            Self::Creature(Species::Bird) => "Flies around, rests on ground",
+            Self::Lava => "Hot liquid, cools into stone",
+            Self::Stone => "Solid rock, acid eats it slowly",
+            Self::Obsidian => "Rock from lava cooled by water",
+            Self::Glass => "Solid made by melting sand",
+            Self::Ice => "Frozen water, melts when warm",
+            Self::Snow => "Light powder, packs into ice",
+            Self::Cryo => "Freezing liquid, puts out fire",
+            Self::Oil => "Floats on water, burns long and smoky",
+            Self::Fuel => "Light liquid, evaporates into methane",
+            Self::Methane => "Gas, explodes when it meets fire",
+            Self::Smoke => "Gas left by fire, fades in the open",
+            Self::Emitter => "Emits the material picked before it",
+            Self::Drain => "Swallows anything that moves into it",
+            Self::Clone => "Copies the first material it touches",
+            Self::BlackHole => "Pulls particles in and swallows them",
+            Self::Repulsor => "Pushes particles away",
+            Self::Fan(_) => "Blows gases and light powders forward",
+            Self::Slime => "Sticky fluid, oozes slowly",
+            Self::Honey => "Very thick fluid, clings to walls",
+            Self::Mud => "Thick wet soil, dries into soil",
+            Self::WetSand => "Sand soaked by water, clumps",
+            Self::Crate => "Rigid body, floats on water",
+            Self::Boulder => "Heavy rigid body, sinks in water",
+            Self::Sawdust => "Light powder from broken crates, burns",
+            Self::Gravel => "Powder from broken boulders",
+            Self::Burning => "Burns until its fuel runs out",
+            Self::Charcoal => "Powder left by burnt wood, smoulders",
+            Self::Base => "Liquid, neutralises acid",
+            Self::Salt => "Powder left when acid meets base",
+            Self::ToxicGas => "Given off by acid, kills life",
+            Self::Host => "Wanders the ground, catches the plague",
+            Self::Immune => "Recovered host, immune for a while",
+            Self::Cure => "Liquid, heals the plague it touches",
+            Self::Border => "Edge of the grid",
+        }
+    }
+
+    /// Whether electric charge can move through the cell.
+    pub fn conductive(&self) -> bool {
+        match &self {
+            Self::Metal | Self::Power | Self::Water => true,
+            Self::Switch(on) => *on,
+            Self::Fan(_) => false,
+            _ => self.dir().is_some(),
+        }
+    }
+
+    /// Whether the cell is a part of a living plant.
+    pub fn plant(&self) -> bool {
+        matches!(self, Self::Seed | Self::Stem(_) | Self::Leaf | Self::Vine(_))
+    }
+
+    /// Whether the cell moves on its own, black holes swallow these.
+    pub fn loose(&self) -> bool {
+        matches!(self, Self::Sand | Self::WetSand | Self::Gunpowder | Self::Ash | Self::Seed | Self::Soil | Self::Snow | Self::Sawdust | Self::Gravel | Self::Charcoal | Self::Salt)
+            || matches!(self, Self::Host | Self::Immune | Self::Plague)
+            || (self.hardness() >= Hardness::Slime as i32 && self.hardness() < Hardness::Nothing as i32)
+    }
+
+    /// Whether wind from fans moves the cell.
+    pub fn light(&self) -> bool {
+        matches!(self, Self::Ash | Self::Snow | Self::Seed | Self::Sawdust)
+            || (self.hardness() >= Hardness::Gas as i32 && self.hardness() < Hardness::Nothing as i32)
+    }
+
+    /// Whether emitters and clones can give out the material.
+    pub fn emittable(&self) -> bool {
+        !matches!(self, Self::Air | Self::Border | Self::Emitter | Self::Drain | Self::Clone)
+    }
+
+    /// Whether vines can hold onto the cell.
+    pub fn climbable(&self) -> bool {
+        matches!(self, Self::Wall | Self::Barrier | Self::Wood | Self::Metal | Self::Stone | Self::Obsidian | Self::Glass)
+    }
+
+    /// Whether the cell is a part of a circuit, even when it does not conduct right now.
+    pub fn electric(&self) -> bool {
+        self.conductive() || matches!(self, Self::Switch(_) | Self::Lamp(_))
+    }
+
+    /// Direction of logic components, they take charge from behind and pass it forward.
+    ///
+    /// Inputs are the cell right behind and the two cells diagonally behind.
+    pub fn dir(&self) -> Option<Dir> {
+        match &self {
+            Self::Diode(dir) | Self::And(dir) | Self::Or(dir) | Self::Not(dir) | Self::Delay(dir) | Self::Fan(dir) => Some(*dir),
+            _ => None,
+        }
+    }
+
+    /// Returns the same state turned clockwise, states without a direction are returned unchanged.
+    pub fn rotated(&self) -> Self {
+        match &self {
+            Self::Diode(dir) => Self::Diode(dir.rotated()),
+            Self::And(dir) => Self::And(dir.rotated()),
+            Self::Or(dir) => Self::Or(dir.rotated()),
+            Self::Not(dir) => Self::Not(dir.rotated()),
+            Self::Delay(dir) => Self::Delay(dir.rotated()),
+            Self::Fan(dir) => Self::Fan(dir.rotated()),
+            _ => *self,
+        }
+    }
+
+    /// Compares states ignoring their payload, so that a rotated brush still matches its button.
+    pub fn same_kind(&self, other: &Self) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    /// Returns a list of all possible cell states in order. (except border and burning cells)
     pub fn list() -> Vec<Self> {
-        vec![Self::Wall, Self::Sand, Self::Water, Self::Plague, Self::Fire(2), Self::Gunpowder, Self::Acid, Self::Wood, Self::Barrier]
+        vec![
+            Self::Wall, Self::Sand, Self::Water, Self::Plague, Self::Fire(2), Self::Gunpowder, Self::Acid, Self::Wood,
+            Self::Metal, Self::Power, Self::Diode(Dir::Right), Self::And(Dir::Right), Self::Or(Dir::Right),
+            Self::Not(Dir::Right), Self::Delay(Dir::Right), Self::Switch(true), Self::Lamp(false), Self::Seed, Self::Soil,
+            Self::Vine(VINE_LENGTH), Self::Creature(Species::Ant), Self::Creature(Species::Fish),
+            Self::Creature(Species::Bird), Self::Lava, Self::Stone, Self::Obsidian, Self::Glass, Self::Ice,
+            Self::Snow, Self::Cryo, Self::Oil, Self::Fuel, Self::Methane, Self::Smoke, Self::Emitter,
+            Self::Drain, Self::Clone, Self::BlackHole, Self::Repulsor, Self::Fan(Dir::Right), Self::Slime,
+            Self::Honey, Self::Mud, Self::WetSand, Self::Crate, Self::Boulder, Self::Sawdust, Self::Gravel,
+            Self::Charcoal, Self::Base, Self::Salt, Self::ToxicGas, Self::Host,
+            Self::Immune, Self::Cure, Self::Barrier,
+        ]
     }
 }
 
@@ -111,8 +578,112 @@ impl std::fmt::Display for CellStates {
             Self::Acid => write!(f, "Acid"),
             Self::Wood => write!(f, "Wood"),
             Self::Ash => write!(f, "Ash"),
+            Self::Metal => write!(f, "Metal"),
+            Self::Power => write!(f, "Power"),
+            Self::Diode(_) => write!(f, "Diode"),
+            Self::And(_) => write!(f, "AND"),
+            Self::Or(_) => write!(f, "OR"),
+            Self::Not(_) => write!(f, "NOT"),
+            Self::Delay(_) => write!(f, "Delay"),
+            Self::Switch(_) => write!(f, "Switch"),
+            Self::Lamp(_) => write!(f, "Lamp"),
+            Self::Seed => write!(f, "Seed"),
+            Self::Soil => write!(f, "Soil"),
+            Self::Stem(_) => write!(f, "Stem"),
+            Self::Leaf => write!(f, "Leaf"),
+            Self::Vine(_) => write!(f, "Vine"),
+            Self::Creature(species) => write!(f, "{species}"),
+            Self::Lava => write!(f, "Lava"),
+            Self::Stone => write!(f, "Stone"),
+            Self::Obsidian => write!(f, "Obsidian"),
+            Self::Glass => write!(f, "Glass"),
+            Self::Ice => write!(f, "Ice"),
+            Self::Snow => write!(f, "Snow"),
+            Self::Cryo => write!(f, "Cryo"),
+            Self::Oil => write!(f, "Oil"),
+            Self::Fuel => write!(f, "Fuel"),
+            Self::Methane => write!(f, "Methane"),
+            Self::Smoke => write!(f, "Smoke"),
+            Self::Emitter => write!(f, "Emitter"),
+            Self::Drain => write!(f, "Drain"),
+            Self::Clone => write!(f, "Clone"),
+            Self::BlackHole => write!(f, "Black hole"),
+            Self::Repulsor => write!(f, "Repulsor"),
+            Self::Fan(_) => write!(f, "Fan"),
+            Self::Slime => write!(f, "Slime"),
+            Self::Honey => write!(f, "Honey"),
+            Self::Mud => write!(f, "Mud"),
+            Self::WetSand => write!(f, "Wet sand"),
+            Self::Crate => write!(f, "Crate"),
+            Self::Boulder => write!(f, "Boulder"),
+            Self::Sawdust => write!(f, "Sawdust"),
+            Self::Gravel => write!(f, "Gravel"),
+            Self::Burning => write!(f, "Burning"),
+            Self::Charcoal => write!(f, "Charcoal"),
+            Self::Base => write!(f, "Base"),
+            Self::Salt => write!(f, "Salt"),
+            Self::ToxicGas => write!(f, "Toxic gas"),
+            Self::Host => write!(f, "Host"),
+            Self::Immune => write!(f, "Immune"),
+            Self::Cure => write!(f, "Cure"),
+        }
+    }
+}
+
+/// Direction on the grid, `Down` is towards higher y.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Dir {
+    Up,
+    Right,
+    Down,
+    Left,
+}
+
+impl Dir {
+    pub fn offset(&self) -> (i32, i32) {
+        match &self {
+            Self::Up => (0, -1),
+            Self::Right => (1, 0),
+            Self::Down => (0, 1),
+            Self::Left => (-1, 0),
         }
     }
+
+    /// Turns the direction clockwise.
+    pub fn rotated(&self) -> Dir {
+        match &self {
+            Self::Up => Self::Right,
+            Self::Right => Self::Down,
+            Self::Down => Self::Left,
+            Self::Left => Self::Up,
+        }
+    }
+
+    pub fn opposite(&self) -> Dir {
+        self.rotated().rotated()
+    }
+
+    pub fn arrow(&self) -> char {
+        match &self {
+            Self::Up => '^',
+            Self::Right => '>',
+            Self::Down => 'v',
+            Self::Left => '<',
+        }
+    }
+}
+
+/// How a material burns, see `CellStates::burn`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Burn {
+    /// Chance in percent to catch fire from a flame next to it, every step of the flame.
+    pub ignition: i32,
+    /// Steps it burns in place.
+    pub fuel: i32,
+    /// Level of the flames it gives off.
+    pub heat: u8,
+    /// What is left once the fuel runs out.
+    pub residue: CellStates,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -0,0 +1,301 @@
+use raylib::prelude::*;
+use rand::prelude::*;
+
+use crate::*;
+
+/// Bits of `Cell::signal` kept for every input of a logic component.
+const HISTORY_BITS: u32 = 10;
+/// Ticks an input stays on after a head passed through it.
+///
+/// Charge comes in pulses every 3 ticks, so a steady signal never looks off.
+const HOLD_TICKS: u32 = 3;
+/// Ticks it takes a `Delay` to pass a head forward.
+pub const DELAY_TICKS: u32 = 8;
+
+/// Electric charge of a cell, moving through conductors by the Wireworld rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Charge {
+    Idle,
+    Head,
+    Tail,
+}
+
+impl Charge {
+    /// Colour drawn instead of the cell's own while charged.
+    pub fn color(&self) -> Option<Color> {
+        match &self {
+            Self::Idle => None,
+            Self::Head => Some(Color{r: 180, g: 240, b: 255, a: 255}),
+            Self::Tail => Some(Color{r: 60, g: 120, b: 255, a: 255}),
+        }
+    }
+
+    /// Charge of a conductor in the next tick, `fire` tells whether it should become a head when idle.
+    fn next(&self, fire: bool) -> Charge {
+        match &self {
+            Self::Head => Self::Tail,
+            Self::Tail => Self::Idle,
+            Self::Idle => match fire {
+                true => Self::Head,
+                false => Self::Idle,
+            },
+        }
+    }
+}
+
+impl Grid {
+    /// Moves all charge by one tick.
+    ///
+    /// Unlike `step` this updates every cell at once, otherwise signals would not travel along wires.
+    pub fn electric_tick(&mut self) {
+        let mut changes = Vec::new();
+        for x in 0..self.size.0 {
+            for y in 0..self.size.1 {
+                let cell = self.cells[x as usize][y as usize];
+                let next = self.next_electric(x, y, &cell);
+                if next != (cell.state, cell.charge, cell.signal) {
+                    changes.push((x, y, next));
+                }
+            }
+        }
+        for (x, y, (state, charge, signal)) in changes {
+            let cell = &mut self.cells[x as usize][y as usize];
+            let discharged = charge == Charge::Head && cell.charge != Charge::Head;
+            cell.charge = charge;
+            cell.signal = signal;
+            self.set(x, y, state);
+            if discharged {
+                self.discharge(x, y);
+            }
+        }
+    }
+
+    /// State, charge and signal history of the cell in the next tick.
+    fn next_electric(&self, x: i32, y: i32, cell: &Cell) -> (CellStates, Charge, u32) {
+        match cell.state {
+            CellStates::Power => (cell.state, Charge::Head, 0),
+            CellStates::Metal | CellStates::Switch(true) => {
+                let heads = self.heads_into(x, y);
+                (cell.state, cell.charge.next(heads == 1 || heads == 2), 0)
+            }
+            // water spreads charge in every direction, so it must not need exact counts
+            CellStates::Water => (cell.state, cell.charge.next(self.heads_into(x, y) > 0), 0),
+            CellStates::Lamp(_) => {
+                let signal = Self::record(cell.signal, 0, self.heads_into(x, y) > 0);
+                (CellStates::Lamp(Self::active(signal, 0)), Charge::Idle, signal)
+            }
+            CellStates::Diode(dir) | CellStates::And(dir) | CellStates::Or(dir) | CellStates::Not(dir) | CellStates::Delay(dir) => {
+                // inputs are behind and diagonally behind, away from the cell in front
+                let back = dir.opposite().offset();
+                let side = dir.rotated().offset();
+                let inputs = [back, (back.0 + side.0, back.1 + side.1), (back.0 - side.0, back.1 - side.1)];
+                let mut signal = cell.signal;
+                let mut connected = [false; 3];
+                for (idx, input) in inputs.iter().enumerate() {
+                    let from = (x + input.0, y + input.1);
+                    connected[idx] = self.emits_to(from, (x, y));
+                    let head = connected[idx] && self.cells[from.0 as usize][from.1 as usize].charge == Charge::Head;
+                    signal = Self::record(signal, idx as u32, head);
+                }
+                let active: Vec<bool> = (0..3).map(|idx| connected[idx] && Self::active(signal, idx as u32)).collect();
+                let charge = match cell.state {
+                    CellStates::Diode(_) => cell.charge.next(active[0]),
+                    CellStates::Not(_) => cell.charge.next(!active.contains(&true)),
+                    CellStates::Or(_) => cell.charge.next(active.contains(&true)),
+                    CellStates::And(_) => {
+                        let count = connected.iter().filter(|c| **c).count();
+                        cell.charge.next(count >= 2 && (0..3).all(|idx| !connected[idx] || active[idx]))
+                    }
+                    // a delay repeats its input exactly, without waiting to be idle
+                    _ => match (signal >> DELAY_TICKS) & 1 {
+                        1 => Charge::Head,
+                        _ => cell.charge.next(false),
+                    },
+                };
+                (cell.state, charge, signal)
+            }
+            _ => (cell.state, Charge::Idle, 0),
+        }
+    }
+
+    /// Pushes a new observation of an input into the signal history.
+    fn record(signal: u32, input: u32, head: bool) -> u32 {
+        let mask = (1 << HISTORY_BITS) - 1;
+        let history = (signal >> (input * HISTORY_BITS)) & mask;
+        let history = ((history << 1) | head as u32) & mask;
+        (signal & !(mask << (input * HISTORY_BITS))) | (history << (input * HISTORY_BITS))
+    }
+
+    /// Whether the input saw a head in the last few ticks.
+    fn active(signal: u32, input: u32) -> bool {
+        (signal >> (input * HISTORY_BITS)) & ((1 << HOLD_TICKS) - 1) != 0
+    }
+
+    /// Whether a head in `from` charges the cell at `to`.
+    ///
+    /// Logic components only charge the cell in front of them, so that charge can't flow back to their inputs.
+    pub fn emits_to(&self, from: (i32, i32), to: (i32, i32)) -> bool {
+        match self.get_cell(from.0, from.1) {
+            Some(cell) => match cell.state.dir() {
+                Some(dir) => (from.0 + dir.offset().0, from.1 + dir.offset().1) == to,
+                None => cell.state.conductive(),
+            },
+            None => false,
+        }
+    }
+
+    /// Effects of a new electron head on its surroundings.
+    fn discharge(&mut self, x: i32, y: i32) {
+        for burnable in self.find_all_around_where(x, y, |state| matches!(state, CellStates::Gunpowder | CellStates::Wood)) {
+            self.ignite(burnable);
+        }
+        // end of a wire
+        if self.cells[x as usize][y as usize].state == CellStates::Metal && self.electric_around(x, y) == 1 {
+            let air = self.find_all_around(x, y, &CellStates::Air);
+            if !air.is_empty() {
+                let target = air[self.rng.gen_range(0..air.len())];
+                self.set(target.0, target.1, CellStates::Spark);
+            }
+        }
+    }
+
+    /// Number of heads around the cell that charge it.
+    pub fn heads_into(&self, x: i32, y: i32) -> i32 {
+        self.find_all_around_where(x, y, |_| true)
+            .into_iter()
+            .filter(|pos| self.cells[pos.0 as usize][pos.1 as usize].charge == Charge::Head && self.emits_to(*pos, (x, y)))
+            .count() as i32
+    }
+
+    /// Number of electric cells around the cell, a wire with only one of them ends there.
+    pub fn electric_around(&self, x: i32, y: i32) -> i32 {
+        self.find_all_around_where(x, y, |state| state.electric()).len() as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    /// Ticks the circuit settles in after a change.
+    const SETTLE_TICKS: usize = 40;
+
+    fn lit(grid: &Grid, pos: (i32, i32)) -> bool {
+        grid.cells[pos.0 as usize][pos.1 as usize].state == CellStates::Lamp(true)
+    }
+
+    /// Ticks at which the lamp turned on or off, over `ticks` ticks.
+    fn edges(grid: &mut Grid, lamp: (i32, i32), ticks: usize, on: bool) -> Vec<usize> {
+        let mut result = Vec::new();
+        let mut was = lit(grid, lamp);
+        for tick in 0..ticks {
+            grid.electric_tick();
+            let now = lit(grid, lamp);
+            if now == on && was != on {
+                result.push(tick);
+            }
+            was = now;
+        }
+        result
+    }
+
+    #[test]
+    fn clock_oscillates() {
+        let mut grid = Grid::new((100, 100));
+        grid.seed(1);
+        scenes::clock(&mut grid);
+        // the lamp behind the diode is on for a pulse, the one behind the NOT gate is off for one
+        for (lamp, on) in [((42, 35), true), ((42, 48), false)] {
+            let mut grid = grid.clone();
+            let switched = edges(&mut grid, lamp, 100, on);
+            assert!(switched.len() >= 7, "lamp {:?} switched {} times", lamp, switched.len());
+            for pair in switched.windows(2) {
+                assert_eq!(pair[1] - pair[0], 12, "lamp {:?} switched at {:?}", lamp, switched);
+            }
+            // and switches back once in every period
+            assert_eq!(edges(&mut grid, lamp, 12, !on).len(), 1);
+        }
+    }
+
+    #[test]
+    fn clock_pulse_is_three_ticks() {
+        let mut grid = Grid::new((100, 100));
+        grid.seed(1);
+        scenes::clock(&mut grid);
+        for _ in 0..SETTLE_TICKS {
+            grid.electric_tick();
+        }
+        let mut top = 0;
+        let mut bottom = 0;
+        for _ in 0..12 {
+            grid.electric_tick();
+            top += lit(&grid, (42, 35)) as i32;
+            bottom += lit(&grid, (42, 48)) as i32;
+        }
+        assert_eq!(top, 3);
+        assert_eq!(bottom, 9);
+    }
+
+    /// Lamps of the AND, OR and NOT gates of the gates scene, with its switches set to the inputs.
+    fn gates(a: bool, b: bool) -> (bool, bool, bool) {
+        let mut grid = Grid::new((100, 100));
+        grid.seed(1);
+        scenes::gates(&mut grid);
+        for row in [30, 50] {
+            grid.set(41, row - 1, CellStates::Switch(a));
+            grid.set(41, row + 1, CellStates::Switch(b));
+        }
+        grid.set(41, 70, CellStates::Switch(a));
+        for _ in 0..SETTLE_TICKS {
+            grid.electric_tick();
+        }
+        (lit(&grid, (56, 30)), lit(&grid, (56, 50)), lit(&grid, (56, 70)))
+    }
+
+    #[test]
+    fn and_gate() {
+        assert!(!gates(false, false).0);
+        assert!(!gates(true, false).0);
+        assert!(!gates(false, true).0);
+        assert!(gates(true, true).0);
+    }
+
+    #[test]
+    fn or_gate() {
+        assert!(!gates(false, false).1);
+        assert!(gates(true, false).1);
+        assert!(gates(false, true).1);
+        assert!(gates(true, true).1);
+    }
+
+    #[test]
+    fn not_gate() {
+        assert!(gates(false, false).2);
+        assert!(!gates(true, false).2);
+    }
+
+    /// Whether a lamp at the end of a powered wire lights up through the diode in the middle.
+    fn through_diode(dir: Dir) -> bool {
+        let mut grid = Grid::new((30, 20));
+        grid.seed(1);
+        grid.set(5, 10, CellStates::Power);
+        for x in 6..10 {
+            grid.set(x, 10, CellStates::Metal);
+        }
+        grid.set(10, 10, CellStates::Diode(dir));
+        for x in 11..15 {
+            grid.set(x, 10, CellStates::Metal);
+        }
+        grid.set(15, 10, CellStates::Lamp(false));
+        for _ in 0..SETTLE_TICKS {
+            grid.electric_tick();
+        }
+        lit(&grid, (15, 10))
+    }
+
+    #[test]
+    fn diode_passes_forward_only() {
+        assert!(through_diode(Dir::Right));
+        assert!(!through_diode(Dir::Left));
+    }
+}
@@ -0,0 +1,219 @@
+use rand::prelude::*;
+
+use crate::*;
+
+/// Census samples kept for the graph.
+pub const CENSUS_LENGTH: usize = 240;
+/// Generations between two census samples.
+const CENSUS_INTERVAL: i32 = 8;
+
+/// Settings of the plague, times are in steps of the infected cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Contagion {
+    /// Chance in percent that an infectious cell infects a host next to it, every step.
+    pub infection: i32,
+    /// Steps before an infected cell starts to infect others.
+    pub incubation: i32,
+    /// Steps an infected cell stays infectious before it dies or recovers.
+    pub duration: i32,
+    /// Chance in percent that the plague ends in death.
+    pub mortality: i32,
+    /// Steps a recovered cell is immune before it can catch the plague again.
+    pub immunity: i32,
+}
+
+impl Default for Contagion {
+    fn default() -> Contagion {
+        Contagion {
+            infection: 10,
+            incubation: 30,
+            duration: 90,
+            mortality: 20,
+            immunity: 600,
+        }
+    }
+}
+
+/// Number of hosts in each stage of the plague at one moment.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Census {
+    pub susceptible: i32,
+    pub infected: i32,
+    pub recovered: i32,
+    /// Hosts killed by the plague since the census started.
+    pub dead: i32,
+}
+
+impl Grid {
+    /// Counts the hosts by their stage of the plague every few generations, for the graph.
+    pub fn census_tick(&mut self) {
+        self.census_generations += 1;
+        if self.census_generations < CENSUS_INTERVAL {
+            return;
+        }
+        self.census_generations = 0;
+        let mut census = Census {
+            dead: self.plague_deaths,
+            ..Census::default()
+        };
+        for column in self.cells.iter() {
+            for cell in column.iter() {
+                match cell.state {
+                    CellStates::Host => census.susceptible += 1,
+                    CellStates::Plague => census.infected += 1,
+                    CellStates::Immune => census.recovered += 1,
+                    _ => (),
+                }
+            }
+        }
+        self.census.push_back(census);
+        if self.census.len() > CENSUS_LENGTH {
+            self.census.pop_front();
+        }
+    }
+
+    /// Starts the census over, forgetting the past counts and the dead.
+    pub fn clear_census(&mut self) {
+        self.census.clear();
+        self.plague_deaths = 0;
+    }
+
+    /// Steps an infected cell. It incubates, then infects the hosts around it and in the end dies or recovers.
+    pub fn infect(&mut self, cell: (i32, i32)) {
+        let age = self.cells[cell.0 as usize][cell.1 as usize].data.age as i32;
+        let contagion = self.contagion;
+        if age >= contagion.incubation + contagion.duration {
+            if self.rng.gen_range(0..100) < contagion.mortality {
+                self.plague_deaths += 1;
+                self.set(cell.0, cell.1, CellStates::Ash);
+            } else {
+                self.set(cell.0, cell.1, CellStates::Immune);
+            }
+            return;
+        }
+        if age >= contagion.incubation {
+            for host in self.find_all_around(cell.0, cell.1, &CellStates::Host) {
+                if self.rng.gen_range(0..100) < contagion.infection {
+                    self.set(host.0, host.1, CellStates::Plague);
+                }
+            }
+        }
+        self.wander(cell);
+    }
+
+    /// Lets the host fall like a powder, on the ground it walks into an empty neighbour now and then, so that the hosts mix.
+    pub fn wander(&mut self, cell: (i32, i32)) {
+        if let Some(down) = self.fall(cell) {
+            let below = (cell.0 + down.0, cell.1 + down.1);
+            if self.drained(cell, below) {
+                return;
+            }
+            if matches!(self.get_cell(below.0, below.1), Some(other) if other.state.hardness() > Hardness::Solid as i32) {
+                self.swap(cell.0, cell.1, below);
+                return;
+            }
+        }
+        if self.rng.gen_range(0..2) == 0 {
+            return;
+        }
+        // sideways, climbing or stepping down one cell, and anywhere in zero gravity
+        let step = match self.gravity {
+            Some(dir) => turned(dir.offset(), (self.rng.gen_range(0..2) * 2 - 1) * self.rng.gen_range(1..4)),
+            None => (self.rng.gen_range(-1..2), self.rng.gen_range(-1..2)),
+        };
+        let target = (cell.0 + step.0, cell.1 + step.1);
+        if matches!(self.get_cell(target.0, target.1), Some(other) if other.state == CellStates::Air) {
+            self.swap(cell.0, cell.1, target);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A row of a host, a plague cell and a host, with no room to wander.
+    fn ward(mortality: i32) -> Grid {
+        let mut grid = Grid::new((3, 1));
+        grid.seed(7);
+        grid.contagion = Contagion {
+            infection: 100,
+            incubation: 3,
+            duration: 5,
+            mortality,
+            immunity: 4,
+        };
+        grid.set(0, 0, CellStates::Host);
+        grid.set(1, 0, CellStates::Plague);
+        grid.set(2, 0, CellStates::Host);
+        grid
+    }
+
+    #[test]
+    fn plague_incubates_before_infecting() {
+        let mut grid = ward(0);
+        for _ in 0..2 {
+            assert_eq!(grid.step_cell(1, 0), vec![]);
+        }
+        let changes = grid.step_cell(1, 0);
+        assert_eq!(
+            changes,
+            vec![
+                Change::Transformed { at: (0, 0), from: CellStates::Host, to: CellStates::Plague },
+                Change::Transformed { at: (2, 0), from: CellStates::Host, to: CellStates::Plague },
+            ]
+        );
+    }
+
+    #[test]
+    fn plague_ends_in_immunity() {
+        let mut grid = ward(0);
+        for _ in 0..7 {
+            grid.step_cell(1, 0);
+        }
+        assert_eq!(grid.cells[1][0].state, CellStates::Plague);
+        grid.step_cell(1, 0);
+        assert_eq!(grid.cells[1][0].state, CellStates::Immune);
+        assert_eq!(grid.plague_deaths, 0);
+    }
+
+    #[test]
+    fn immunity_wears_off() {
+        let mut grid = ward(0);
+        grid.set(1, 0, CellStates::Immune);
+        for _ in 0..3 {
+            grid.step_cell(1, 0);
+        }
+        assert_eq!(grid.cells[1][0].state, CellStates::Immune);
+        grid.step_cell(1, 0);
+        assert_eq!(grid.cells[1][0].state, CellStates::Host);
+    }
+
+    #[test]
+    fn hosts_fall_with_gravity() {
+        let mut grid = Grid::new((3, 3));
+        grid.seed(7);
+        grid.set(1, 0, CellStates::Host);
+        grid.step_cell(1, 0);
+        assert_eq!(grid.cells[1][1].state, CellStates::Host);
+        grid.gravity = Some(Dir::Up);
+        grid.step_cell(1, 1);
+        assert_eq!(grid.cells[1][0].state, CellStates::Host);
+    }
+
+    #[test]
+    fn census_counts_the_dead() {
+        let mut grid = ward(100);
+        for _ in 0..8 {
+            grid.step_cell(1, 0);
+        }
+        assert_eq!(grid.cells[1][0].state, CellStates::Ash);
+        for _ in 0..CENSUS_INTERVAL {
+            grid.census_tick();
+        }
+        assert_eq!(grid.census.back(), Some(&Census { susceptible: 0, infected: 2, recovered: 0, dead: 1 }));
+        grid.clear_census();
+        assert!(grid.census.is_empty());
+        assert_eq!(grid.plague_deaths, 0);
+    }
+}
@@ -0,0 +1,90 @@
+use rand::prelude::*;
+
+use crate::*;
+
+/// Distance up to which black holes and repulsors reach.
+const FIELD_RADIUS: i32 = 10;
+/// Distance up to which fans blow.
+const WIND_RANGE: i32 = 16;
+
+/// Push of the field cells on one position, see `Grid::field_tick`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Force {
+    /// Pull of black holes and repulsors, moving everything loose.
+    pub pull: (f32, f32),
+    /// Wind of fans, moving only light cells.
+    pub wind: (f32, f32),
+}
+
+impl Grid {
+    /// Works out the push of every field cell on the positions around it.
+    ///
+    /// Field cells don't move on their own, so this runs once per generation like the electric tick.
+    pub fn field_tick(&mut self) {
+        let mut forces = vec![vec![Force::default(); self.size.1 as usize]; self.size.0 as usize];
+        for x in 0..self.size.0 {
+            for y in 0..self.size.1 {
+                match self.cells[x as usize][y as usize].state {
+                    CellStates::BlackHole => Self::attract(&mut forces, (x, y), 1.),
+                    CellStates::Repulsor => Self::attract(&mut forces, (x, y), -1.),
+                    CellStates::Fan(dir) => self.blow(&mut forces, (x, y), dir),
+                    _ => (),
+                }
+            }
+        }
+        self.forces = forces;
+    }
+
+    /// Pulls everything around `at` towards it, or pushes it away with a negative `sign`.
+    fn attract(forces: &mut [Vec<Force>], at: (i32, i32), sign: f32) {
+        for dx in -FIELD_RADIUS..=FIELD_RADIUS {
+            for dy in -FIELD_RADIUS..=FIELD_RADIUS {
+                let distance = ((dx * dx + dy * dy) as f32).sqrt();
+                if distance == 0. || distance > FIELD_RADIUS as f32 {
+                    continue;
+                }
+                let force = match forces.get_mut((at.0 + dx) as usize).and_then(|column| column.get_mut((at.1 + dy) as usize)) {
+                    Some(force) => force,
+                    None => continue,
+                };
+                let strength = sign * (1. - distance / (FIELD_RADIUS + 1) as f32);
+                force.pull.0 -= dx as f32 / distance * strength;
+                force.pull.1 -= dy as f32 / distance * strength;
+            }
+        }
+    }
+
+    /// Blows in a three cells wide stream in front of the fan, each lane stops at the first static solid.
+    fn blow(&self, forces: &mut [Vec<Force>], at: (i32, i32), dir: Dir) {
+        let forward = dir.offset();
+        let across = dir.rotated().offset();
+        for lane in -1..2 {
+            for distance in 1..=WIND_RANGE {
+                let pos = (at.0 + forward.0 * distance + across.0 * lane, at.1 + forward.1 * distance + across.1 * lane);
+                match self.get_cell(pos.0, pos.1) {
+                    Some(cell) if cell.state.hardness() > Hardness::Solid as i32 || cell.state.loose() => (),
+                    _ => break,
+                }
+                let strength = 1. - distance as f32 / (WIND_RANGE + 1) as f32;
+                let force = &mut forces[pos.0 as usize][pos.1 as usize];
+                force.wind.0 += forward.0 as f32 * strength;
+                force.wind.1 += forward.1 as f32 * strength;
+            }
+        }
+    }
+
+    /// Direction the fields push the cell in this step, chosen more often the stronger they are.
+    pub fn push(&mut self, cell: (i32, i32)) -> Option<(i32, i32)> {
+        let force = *self.forces.get(cell.0 as usize)?.get(cell.1 as usize)?;
+        let mut total = force.pull;
+        if self.cells[cell.0 as usize][cell.1 as usize].state.light() {
+            total.0 += force.wind.0;
+            total.1 += force.wind.1;
+        }
+        let length = total.0.hypot(total.1);
+        if length == 0. || self.rng.gen::<f32>() >= length.min(1.) {
+            return None;
+        }
+        Some(((total.0 / length).round() as i32, (total.1 / length).round() as i32))
+    }
+}
@@ -1,15 +1,69 @@
+use std::collections::VecDeque;
+
 use raylib::prelude::*;
 use rand::prelude::*;
 
 use crate::*;
 
+/// Times acid can mix with water before it turns into water itself.
+const MAX_DILUTION: i32 = 4;
+/// Gravity strength at which falling cells move with gravity in every step.
+pub const FULL_GRAVITY: i32 = 100;
+
 #[derive(Clone, Debug)]
 pub struct Grid {
     pub cells: Vec<Vec<Cell>>,
-    pub rng: ThreadRng,
+    pub rng: StdRng,
     pub size: (i32, i32),
     pub dim: (f64, f64, f64, f64),
     pub cell_dim: (f64, f64),
+    /// Changes done by the current step, recorded only while in `step_cell`.
+    journal: Option<Vec<Change>>,
+    /// Steps done since the last electric tick.
+    generation_steps: i32,
+    /// Direction powders and liquids fall in, `None` is zero gravity.
+    pub gravity: Option<Dir>,
+    /// Chance in percent that a falling cell moves with gravity in a step, up to `FULL_GRAVITY`.
+    pub gravity_strength: i32,
+    /// Whether unsupported solids fall and long overhangs break off, see `Grid::structure_tick`.
+    pub collapse: bool,
+    /// Push of the field cells on every position, see `Grid::field_tick`.
+    pub forces: Vec<Vec<Force>>,
+    /// Depth of every liquid cell under the surface of its body, see `Grid::pressure_tick`.
+    pub pressure: Vec<Vec<i32>>,
+    /// Last id given to a rigid body, see `Grid::body_id`.
+    pub body_ids: i32,
+    pub contagion: Contagion,
+    /// Past counts of the hosts, the newest last, see `Grid::census_tick`.
+    pub census: VecDeque<Census>,
+    /// Generations since the last census.
+    pub census_generations: i32,
+    /// Hosts the plague killed since the census started, they leave only ash behind.
+    pub plague_deaths: i32,
+}
+
+/// A change done to the grid by stepping a cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// The cell at `from` swapped places with the cell at `to`.
+    Moved { from: (i32, i32), to: (i32, i32) },
+    /// A cell changed its state.
+    Transformed { at: (i32, i32), from: CellStates, to: CellStates },
+    /// A new cell appeared in place of air.
+    Spawned { at: (i32, i32), state: CellStates },
+    /// A cell kept its state but its data changed.
+    Updated { at: (i32, i32), state: CellStates },
+}
+
+impl std::fmt::Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self {
+            Self::Moved { from, to } => write!(f, "moved {:?} -> {:?}", from, to),
+            Self::Transformed { at, from, to } => write!(f, "{:?} {from} -> {to}", at),
+            Self::Spawned { at, state } => write!(f, "{:?} spawned {state}", at),
+            Self::Updated { at, state } => write!(f, "{:?} {state} updated", at),
+        }
+    }
 }
 
 impl Grid {
@@ -18,7 +72,7 @@ impl Grid {
         for _ in 0..width {
             cells.push(vec![Cell::new(); height as usize]);
         }
-        let rng = rand::thread_rng();
+        let rng = StdRng::from_entropy();
 
         Grid {
             cells,
@@ -26,25 +80,69 @@ impl Grid {
             size: (width, height),
             dim: (0.,0.,0.,0.),
             cell_dim: (0., 0.),
+            journal: None,
+            generation_steps: 0,
+            gravity: Some(Dir::Down),
+            gravity_strength: FULL_GRAVITY,
+            collapse: false,
+            forces: vec![vec![Force::default(); height as usize]; width as usize],
+            pressure: vec![vec![0; height as usize]; width as usize],
+            body_ids: 0,
+            contagion: Contagion::default(),
+            census: VecDeque::new(),
+            census_generations: 0,
+            plague_deaths: 0,
         }
     }
 
+    /// Restarts the random generator from `seed`, making the following steps repeatable.
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
     pub fn draw(&self, d: &mut RaylibDrawHandle) {
         const COLOR: CellStates = CellStates::Air;
         d.draw_rectangle(self.dim.0 as i32, self.dim.1 as i32, self.dim.2 as i32, self.dim.3 as i32, CellStates::color(&COLOR));
         for x in 0..self.size.0 {
             for y in 0..self.size.1 {
-                let state = self.cells[x as usize][y as usize].state;
+                let color = self.cells[x as usize][y as usize].color();
+                let color = Self::vary(color, self.cells[x as usize][y as usize].state.variation(), self.cells[x as usize][y as usize].data.seed);
                 let rect = (self.dim.0 + x as f64 * self.cell_dim.0, self.dim.1 + y as f64 * self.cell_dim.1, self.cell_dim.0, self.cell_dim.1);
-                d.draw_rectangle(rect.0 as i32, rect.1 as i32, rect.2.ceil() as i32, rect.3.ceil() as i32, state.color());
+                d.draw_rectangle(rect.0 as i32, rect.1 as i32, rect.2.ceil() as i32, rect.3.ceil() as i32, color);
+                // mark the front of logic components
+                if let Some(dir) = self.cells[x as usize][y as usize].state.dir() {
+                    let mark = (rect.2 / 3., rect.3 / 3.);
+                    let offset = dir.offset();
+                    d.draw_rectangle(
+                        (rect.0 + mark.0 + offset.0 as f64 * mark.0) as i32,
+                        (rect.1 + mark.1 + offset.1 as f64 * mark.1) as i32,
+                        mark.0.ceil() as i32,
+                        mark.1.ceil() as i32,
+                        Color::BLACK,
+                    );
+                }
             }
         }
     }
 
+    /// Changes the brightness of a colour by up to `amount`, following the colour seed of the cell.
+    fn vary(color: Color, amount: i32, seed: u8) -> Color {
+        if amount == 0 {
+            return color;
+        }
+        let shift = seed as i32 * amount * 2 / 255 - amount;
+        Color {
+            r: (color.r as i32 + shift).clamp(0, 255) as u8,
+            g: (color.g as i32 + shift).clamp(0, 255) as u8,
+            b: (color.b as i32 + shift).clamp(0, 255) as u8,
+            a: color.a,
+        }
+    }
+
     pub fn recalculate_dim(&mut self, screen: (i32, i32)) {
         let dim_diff = ((screen.0 as f64 * 0.15), (screen.1 as f64 * 0.05));
         self.dim = (dim_diff.0, dim_diff.1, screen.0 as f64 - dim_diff.0*2., screen.1 as f64 - dim_diff.1*2.);
-        self.cell_dim = ((self.dim.2 as f64 / self.size.0 as f64), (self.dim.3 as f64 / self.size.1 as f64));
+        self.cell_dim = ((self.dim.2 / self.size.0 as f64), (self.dim.3 / self.size.1 as f64));
     }
 
     pub fn get_cell(&self, x: i32, y: i32) -> Option<&Cell> {
@@ -54,162 +152,294 @@ impl Grid {
         None
     }
 
+    /// Number of iterations in one generation, that is every cell stepped once on average.
+    pub fn generation(&self) -> i32 {
+        self.size.0 * self.size.1
+    }
+
+    /// Steps a randomly chosen cell.
     pub fn step(&mut self) {
         let cell = (self.rng.gen_range(0..self.size.0), self.rng.gen_range(0..self.size.1));
+        self.step_at(cell);
+        // charge moves once per generation, so circuits keep up with the rest of the simulation
+        self.generation_steps += 1;
+        if self.generation_steps >= self.generation() {
+            self.generation_steps = 0;
+            self.electric_tick();
+            self.field_tick();
+            self.pressure_tick();
+            self.census_tick();
+            if self.collapse {
+                self.structure_tick();
+            }
+        }
+    }
+
+    /// Steps the cell at the given position and returns everything that changed, in order.
+    ///
+    /// Cells out of bounds are ignored and report no changes. The age of the stepped cell is not reported.
+    pub fn step_cell(&mut self, x: i32, y: i32) -> Vec<Change> {
+        self.journal = Some(Vec::new());
+        self.step_at((x, y));
+        self.journal.take().unwrap_or_default()
+    }
+
+    fn step_at(&mut self, cell: (i32, i32)) {
+        if !self.bounds(cell.0, cell.1) {
+            return;
+        }
         let state = self.cells[cell.0 as usize][cell.1 as usize].state;
+        // what the cell moves like, burning cells move like what is burning
+        let matter = self.cells[cell.0 as usize][cell.1 as usize].matter();
+        let data = &mut self.cells[cell.0 as usize][cell.1 as usize].data;
+        data.age = data.age.saturating_add(1);
         macro_rules! liquid_movement {
             ($x:expr, $y:expr, $state:expr) => {
-                match self.get_cell(cell.0, cell.1 + 1) {
-                    Some(other) => {
-                        if other.state.hardness() > state.hardness() {
-                            self.swap(cell.0, cell.1, (cell.0, cell.1 + 1));
+                // thick fluids cling to the walls they touch
+                let viscosity = matter.viscosity();
+                let stuck = viscosity > 1 && self.rng.gen_range(0..viscosity) != 0 && self.touches_wall(cell);
+                // and move only now and then
+                let slow = viscosity > 2 && self.rng.gen_range(0..viscosity - 1) != 0;
+                // in zero gravity the cell mostly stays put
+                if let (false, Some(down)) = (stuck || slow, self.fall(cell)) {
+                    if self.drained(cell, (cell.0 + down.0, cell.1 + down.1)) {
+                        return;
+                    }
+                    if matches!(self.get_cell(cell.0 + down.0, cell.1 + down.1), Some(other) if other.matter().hardness() > matter.hardness() || matter.sinks_into(&other.matter())) {
+                        self.swap(cell.0, cell.1, (cell.0 + down.0, cell.1 + down.1));
+                        return;
+                    }
+                    let mut side = self.rng.gen_range(0..2) * 2 - 1;
+                    for _ in 0..2 {
+                        let target = turned(down, side);
+                        let target = (cell.0 + target.0, cell.1 + target.1);
+                        if self.drained(cell, target) {
                             return;
                         }
-                    }
-                    None => ()
-                }
-                let mut side = self.rng.gen_range(0..2) * 2 - 1;
-                for _ in 0..2 {
-                    match self.get_cell(cell.0 + side, cell.1 + 1) {
-                        Some(other) => {
-                            match other.state.hardness().cmp(&state.hardness()) {
-                                std::cmp::Ordering::Greater => {
-                                    self.swap(cell.0, cell.1, (cell.0 + side, cell.1 + 1));
-                                    return;
-                                }
-                                std::cmp::Ordering::Less => (),
-                                std::cmp::Ordering::Equal => {
-                                    let rand = self.rng.gen_range(0..state.weight());
-                                    if rand == 0 {
-                                        self.swap(cell.0, cell.1, (cell.0 + side, cell.1 + 1));
+                        match self.get_cell(target.0, target.1) {
+                            Some(other) => {
+                                match other.matter().hardness().cmp(&matter.hardness()) {
+                                    std::cmp::Ordering::Greater => {
+                                        self.swap(cell.0, cell.1, target);
                                         return;
                                     }
+                                    std::cmp::Ordering::Less => (),
+                                    std::cmp::Ordering::Equal if matter.sinks_into(&other.matter()) => {
+                                        self.swap(cell.0, cell.1, target);
+                                        return;
+                                    }
+                                    // lighter liquids stay above, only the same density mixes
+                                    std::cmp::Ordering::Equal if other.matter().density() != matter.density() => (),
+                                    std::cmp::Ordering::Equal => {
+                                        let rand = self.rng.gen_range(0..matter.weight());
+                                        if rand == 0 {
+                                            self.swap(cell.0, cell.1, target);
+                                            return;
+                                        }
+                                    }
                                 }
-                            }
-                        },
-                        None => (),
+                            },
+                            None => (),
+                        }
+                        side *= -1;
                     }
-                    side *= -1;
-                }
-                for _ in 0..2 {
-                    match self.get_cell(cell.0 + side, cell.1) {
-                        Some(other) => {
-                            match other.state.hardness().cmp(&state.hardness()) {
-                                std::cmp::Ordering::Greater => {
-                                    self.swap(cell.0, cell.1, (cell.0 + side, cell.1));
-                                    return;
-                                }
-                                std::cmp::Ordering::Less => (),
-                                std::cmp::Ordering::Equal => {
-                                    let rand = self.rng.gen_range(0..state.weight());
-                                    if rand == 0 {
-                                        self.swap(cell.0, cell.1, (cell.0 + side, cell.1));
+                    // and rarely spread sideways
+                    let spread = if viscosity > 1 && self.rng.gen_range(0..viscosity) != 0 { 0 } else { 2 };
+                    for _ in 0..spread {
+                        let target = turned(down, side * 2);
+                        let target = (cell.0 + target.0, cell.1 + target.1);
+                        if self.drained(cell, target) {
+                            return;
+                        }
+                        match self.get_cell(target.0, target.1) {
+                            Some(other) => {
+                                match other.matter().hardness().cmp(&matter.hardness()) {
+                                    std::cmp::Ordering::Greater => {
+                                        let pressure = self.pressure[cell.0 as usize][cell.1 as usize];
+                                        self.swap(cell.0, cell.1, target);
+                                        // the liquid above pushes it further out
+                                        self.jet(target, turned(down, side * 2), pressure);
                                         return;
                                     }
+                                    std::cmp::Ordering::Less => (),
+                                    std::cmp::Ordering::Equal => {
+                                        let rand = self.rng.gen_range(0..matter.weight());
+                                        if rand == 0 {
+                                            self.swap(cell.0, cell.1, target);
+                                            return;
+                                        }
+                                    }
                                 }
-                            }
-                        },
-                        None => (),
+                            },
+                            None => (),
+                        }
+                        side *= -1;
                     }
-                    side *= -1;
                 }
             }
         }
         macro_rules! powder_movement {
             () => {
-                match self.get_cell(cell.0, cell.1 + 1) {
-                    Some(other) => {
-                        if other.state.hardness() > state.hardness() {
-                            self.swap(cell.0, cell.1, (cell.0, cell.1 + 1));
+                // in zero gravity the cell mostly stays put
+                if let Some(down) = self.fall(cell) {
+                    if self.drained(cell, (cell.0 + down.0, cell.1 + down.1)) {
+                        return;
+                    }
+                    if matches!(self.get_cell(cell.0 + down.0, cell.1 + down.1), Some(other) if other.matter().hardness() > matter.hardness()) {
+                        self.swap(cell.0, cell.1, (cell.0 + down.0, cell.1 + down.1));
+                        return;
+                    }
+                    let mut side = self.rng.gen_range(0..2) * 2 - 1;
+                    for _ in 0..2 {
+                        let target = turned(down, side);
+                        let target = (cell.0 + target.0, cell.1 + target.1);
+                        if self.drained(cell, target) {
+                            return;
+                        }
+                        // steep piles need a deeper drop before the grain slides
+                        if self.drops(target, down, matter, matter.repose().max(1)) {
+                            self.swap(cell.0, cell.1, target);
+                            return;
+                        }
+                        side *= -1;
+                    }
+                    // flat piling powders also roll sideways when they can slide down from there
+                    if matter.repose() == 0 {
+                        let target = turned(down, side * 2);
+                        let target = (cell.0 + target.0, cell.1 + target.1);
+                        let beyond = turned(down, side);
+                        let beyond = (target.0 + beyond.0, target.1 + beyond.1);
+                        if self.drops(target, down, matter, 1) && self.drops(beyond, down, matter, 1) {
+                            self.swap(cell.0, cell.1, target);
                             return;
                         }
                     }
-                    None => ()
                 }
-                let mut side = self.rng.gen_range(0..2) * 2 - 1;
-                for _ in 0..2 {
-                    match self.get_cell(cell.0 + side, cell.1 + 1) {
-                        Some(other) => {
-                            if other.state.hardness() > state.hardness() {
-                                self.swap(cell.0, cell.1, (cell.0 + side, cell.1 + 1));
-                                return;
+                
+            };
+        }
+        // wanders in a random walk, rising against gravity `$buoyancy` times in 10, and mixes with other gases
+        // fields push it before anything else
+        macro_rules! gas_movement {
+            ($buoyancy:expr) => {
+                let (dx, dy) = match (self.push(cell), self.gravity) {
+                    (Some(push), _) => push,
+                    (None, Some(gravity)) if self.rng.gen_range(0..10) < $buoyancy && self.pulled() => {
+                        let spread = self.rng.gen_range(-1..2);
+                        turned(gravity.opposite().offset(), spread)
+                    }
+                    _ => (self.rng.gen_range(-1..2), self.rng.gen_range(-1..2)),
+                };
+                if self.drained(cell, (cell.0 + dx, cell.1 + dy)) {
+                    return;
+                }
+                match self.get_cell(cell.0 + dx, cell.1 + dy) {
+                    Some(other) => {
+                        match other.matter().hardness().cmp(&matter.hardness()) {
+                            std::cmp::Ordering::Greater => {
+                                self.swap(cell.0, cell.1, (cell.0 + dx, cell.1 + dy));
+                            }
+                            std::cmp::Ordering::Equal if other.state != state && self.rng.gen_range(0..4) == 0 => {
+                                self.swap(cell.0, cell.1, (cell.0 + dx, cell.1 + dy));
                             }
-                        },
-                        None => (),
+                            _ => (),
+                        }
                     }
-                    side *= -1;
+                    None => ()
                 }
-                
             };
         }
         match state {
             CellStates::Air => (),
             CellStates::Sand => {
+                if self.rng.gen_range(0..20) == 0 && self.is_around(cell.0, cell.1, &CellStates::Water) {
+                    self.set(cell.0, cell.1, CellStates::WetSand);
+                    return;
+                }
                 powder_movement!();
             },
+            CellStates::WetSand => {
+                // dries away from water
+                if self.rng.gen_range(0..1500) == 0 && !self.is_around(cell.0, cell.1, &CellStates::Water) {
+                    self.set(cell.0, cell.1, CellStates::Sand);
+                    return;
+                }
+                // clumps together, holding overhangs for a while
+                if self.find_all_around(cell.0, cell.1, &CellStates::WetSand).len() >= 3 && self.rng.gen_range(0..10) != 0 {
+                    return;
+                }
+                powder_movement!();
+            }
             CellStates::Water => {
                 liquid_movement!(cell.0, cell.1, state);
             }
-            CellStates::Plague => {
-                let victim = (self.rng.gen_range(0..2) * 2 - 1 + cell.0, self.rng.gen_range(0..2) * 2 - 1  + cell.1);
-                match self.get_cell(victim.0, victim.1) {
-                    Some(other) => {
-                        if other.state.hardness() >= state.hardness() {
-                            self.set(victim.0, victim.1, state);
-                            return;
-                        }
-                    },
-                    None => (),
-                }
-            },
-            CellStates::Fire(level) => {
-                for gp in self.find_all_around(cell.0, cell.1, &CellStates::Gunpowder) {
-                    self.cell_unchecked(gp.0, gp.1).state = CellStates::Spark
-                }
-                for gp in self.find_all_around(cell.0, cell.1, &CellStates::Water) {
-                    self.cell_unchecked(gp.0, gp.1).state = CellStates::Vapor
-                }
-                if self.rng.gen_range(0..4) == 0 {
-                    for gp in self.find_all_around(cell.0, cell.1, &CellStates::Wood) {
-                        self.cell_unchecked(gp.0, gp.1).state = CellStates::Fire(1);
-                        let rand = self.rng.gen_range(0..5);
-                        if rand == 0 {
-                            self.cell_unchecked(cell.0, cell.1).state = CellStates::Ash;
-                        }
-                    }
+            CellStates::Plague => self.infect(cell),
+            CellStates::Host => self.wander(cell),
+            CellStates::Immune => {
+                if self.cells[cell.0 as usize][cell.1 as usize].data.age as i32 >= self.contagion.immunity {
+                    self.set(cell.0, cell.1, CellStates::Host);
+                    return;
                 }
-                for gp in self.find_all_around(cell.0, cell.1, &CellStates::Plague) {
-                    self.cell_unchecked(gp.0, gp.1).state = CellStates::Fire(0);
-                    let rand = self.rng.gen_range(0..5);
-                    if rand == 0 {
-                        self.cell_unchecked(cell.0, cell.1).state = CellStates::Ash;
+                self.wander(cell);
+            }
+            CellStates::Cure => {
+                // used up by healing
+                for sick in self.find_all_around(cell.0, cell.1, &CellStates::Plague) {
+                    self.set(sick.0, sick.1, CellStates::Immune);
+                    if self.rng.gen_range(0..3) == 0 {
+                        self.set(cell.0, cell.1, CellStates::Air);
+                        return;
                     }
                 }
-                let rand = self.rng.gen_range(-1..2);
-                match self.get_cell(cell.0 + rand, cell.1 - 1) {
-                    Some(other) => {
-                        if other.state.hardness() > state.hardness() && self.rng.gen_range(0..50) > 20 {
-                            self.set(cell.0 + rand, cell.1 - 1, state)
-                        }
-                    }
-                    None => {
-
-                    }
+                liquid_movement!(cell.0, cell.1, state);
+            }
+            CellStates::Fire(level) => {
+                self.spread_fire(cell);
+                if self.rng.gen_range(0..50) > 20 {
+                    self.flame(cell, level);
                 }
+                // some of the dying flames leave smoke behind
+                let ember = if self.rng.gen_range(0..4) == 0 { CellStates::Smoke } else { CellStates::Air };
                 match self.rng.gen_range(0..50) {
                     0..=5 => {
-                        self.cell_unchecked(cell.0, cell.1).state = CellStates::Air
-                    }
-                    0..=40 => {
-                        if level == 0 {
-                            self.cell_unchecked(cell.0, cell.1).state = CellStates::Air
-                        }else {
-                            self.cell_unchecked(cell.0, cell.1).state = CellStates::Fire(level - 1)
-                        }
+                        self.set(cell.0, cell.1, ember);
                     }
+                    6..=40 => match level.checked_sub(1) {
+                        Some(level) => self.set(cell.0, cell.1, CellStates::Fire(level)),
+                        None => self.set(cell.0, cell.1, ember),
+                    },
                     _ => {}
                 }
             }
+            CellStates::Burning => {
+                let data = self.cells[cell.0 as usize][cell.1 as usize].data;
+                let heat = self.cells[cell.0 as usize][cell.1 as usize].heat();
+                let material = matter;
+                // water puts it out now and then
+                if self.rng.gen_range(0..3) == 0 && self.is_around(cell.0, cell.1, &CellStates::Water) {
+                    self.set(cell.0, cell.1, material);
+                    return;
+                }
+                self.spread_fire(cell);
+                if data.counter <= 0 {
+                    let residue = material.burn().map_or(CellStates::Ash, |burn| burn.residue);
+                    // a packed heap of gunpowder goes off instead of burning out
+                    let packed = material == CellStates::Gunpowder
+                        && self.find_all_around_where(cell.0, cell.1, |state| matches!(state, CellStates::Gunpowder | CellStates::Burning)).len() >= 4;
+                    self.set(cell.0, cell.1, if packed { CellStates::Spark } else { residue });
+                    return;
+                }
+                self.set_data(cell.0, cell.1, CellData { counter: data.counter - 1, ..data });
+                if self.rng.gen_range(0..3) == 0 {
+                    self.flame(cell, heat);
+                }
+                // keeps moving like what is burning
+                if material.fluid() {
+                    liquid_movement!(cell.0, cell.1, state);
+                } else if material.loose() {
+                    powder_movement!();
+                }
+            }
             CellStates::Gunpowder => {
                 powder_movement!();
             }
@@ -230,46 +460,244 @@ impl Grid {
             CellStates::Vapor => {
                 match self.rng.gen_range(0..400) {
                     0..=1 => {
-                        self.cell_unchecked(cell.0, cell.1).state = CellStates::Air
+                        self.set(cell.0, cell.1, CellStates::Air);
                     }
-                    0..=3 => {
-                        self.cell_unchecked(cell.0, cell.1).state = CellStates::Water
+                    2..=3 => {
+                        self.set(cell.0, cell.1, CellStates::Water);
                     }
                     _ => {}
                 }
-                let rand = self.rng.gen_range(-1..2);
-                match self.get_cell(cell.0 + rand, cell.1 - 1) {
-                    Some(other) => {
-                        if other.state.hardness() > state.hardness() && self.rng.gen_range(0..50) > 20 {
-                            self.swap(cell.0, cell.1, (cell.0 + rand, cell.1 - 1))
-                        }
-                    }
-                    None => {
-
-                    }
-                }
-
+                gas_movement!(6);
             }
             CellStates::Wall => (),
             CellStates::Barrier => (),
             CellStates::Acid => {
+                // times it mixed with water, diluted acid eats slower
+                let dilution = self.cells[cell.0 as usize][cell.1 as usize].data.counter;
+                // neutralised by base, hot enough to boil some of the water it leaves and set fire to what is around
+                let base = self.find_all_around(cell.0, cell.1, &CellStates::Base);
+                if let Some(base) = base.choose(&mut self.rng) {
+                    self.set(base.0, base.1, CellStates::Salt);
+                    let water = if self.rng.gen_range(0..3) == 0 { CellStates::Vapor } else { CellStates::Water };
+                    self.set(cell.0, cell.1, water);
+                    self.kindle(cell);
+                    return;
+                }
+                // mixes into water, getting weaker until it is water as well
+                if self.rng.gen_range(0..10) == 0 {
+                    let water = self.find_all_around(cell.0, cell.1, &CellStates::Water);
+                    if let Some(water) = water.choose(&mut self.rng) {
+                        if dilution >= MAX_DILUTION {
+                            self.set(cell.0, cell.1, CellStates::Water);
+                            return;
+                        }
+                        self.set(water.0, water.1, CellStates::Acid);
+                        for pos in [*water, cell] {
+                            let data = self.cells[pos.0 as usize][pos.1 as usize].data;
+                            self.set_data(pos.0, pos.1, CellData { counter: dilution + 1, ..data });
+                        }
+                    }
+                }
                 liquid_movement!(cell.0, cell.1, state);
-                if self.rng.gen_range(0..5) > 0 {return}
-                let solids = self.cmp_states_around(cell.0, cell.1, Hardness::Solid as i32, std::cmp::Ordering::Equal);
-                if solids.len() == 0 {
+                if self.rng.gen_range(0..5 * (dilution + 1)) > 0 {return}
+                let solids: Vec<(i32, i32)> = self
+                    .cmp_states_around(cell.0, cell.1, Hardness::Solid as i32, std::cmp::Ordering::Equal)
+                    .into_iter()
+                    .filter(|pos| self.cells[pos.0 as usize][pos.1 as usize].state.resistance().is_some())
+                    .collect();
+                if solids.is_empty() {
                     return;
                 }
-                let rand_idx = self.rng.gen_range(0..solids.len());
-                self.cell_unchecked(solids[rand_idx].0, solids[rand_idx].1).state = CellStates::Air;
+                let target = solids[self.rng.gen_range(0..solids.len())];
+                let eaten = self.cells[target.0 as usize][target.1 as usize].state;
+                if self.rng.gen_range(0..eaten.resistance().unwrap_or(1)) != 0 {
+                    return;
+                }
+                self.set(target.0, target.1, if eaten.toxic() { CellStates::ToxicGas } else { CellStates::Air });
 
                 let dissapear = self.rng.gen_range(0..5);
                 if dissapear != 0 {
-                    self.cell_unchecked(cell.0, cell.1).state = CellStates::Air;
+                    self.set(cell.0, cell.1, CellStates::Air);
                 }
             }
             CellStates::Wood => {
 
             }
+            CellStates::Seed => {
+                powder_movement!();
+                self.sprout(cell);
+            }
+            CellStates::Soil => {
+                powder_movement!();
+            }
+            CellStates::Stem(height) => self.grow_stem(cell, height),
+            CellStates::Leaf => (),
+            CellStates::Vine(length) => self.grow_vine(cell, length),
+            // creatures are moved by their agents
+            CellStates::Creature(_) => (),
+            // and rigid bodies by their bodies
+            CellStates::Crate | CellStates::Boulder => (),
+            CellStates::Sawdust | CellStates::Gravel | CellStates::Charcoal | CellStates::Salt => {
+                powder_movement!();
+            }
+            CellStates::Lava => {
+                // quenched by water
+                let water = self.find_all_around(cell.0, cell.1, &CellStates::Water);
+                if !water.is_empty() {
+                    for water in water {
+                        self.set(water.0, water.1, CellStates::Vapor);
+                    }
+                    self.set(cell.0, cell.1, CellStates::Obsidian);
+                    return;
+                }
+                for gas in self.find_all_around(cell.0, cell.1, &CellStates::Methane) {
+                    self.set(gas.0, gas.1, CellStates::Spark);
+                }
+                for burning in self.find_all_around_where(cell.0, cell.1, |state| state.burn().is_some()) {
+                    self.ignite(burning);
+                }
+                if self.rng.gen_range(0..10) == 0 {
+                    for sand in self.find_all_around(cell.0, cell.1, &CellStates::Sand) {
+                        self.set(sand.0, sand.1, CellStates::Glass);
+                    }
+                }
+                if self.rng.gen_range(0..3000) == 0 {
+                    self.set(cell.0, cell.1, CellStates::Stone);
+                    return;
+                }
+                liquid_movement!(cell.0, cell.1, state);
+            }
+            CellStates::Stone => (),
+            CellStates::Obsidian => (),
+            CellStates::Glass => (),
+            CellStates::Ice => {
+                self.melt(cell, CellStates::Water);
+            }
+            CellStates::Snow => {
+                if self.melt(cell, CellStates::Water) {
+                    return;
+                }
+                // packed into ice by the snow above
+                let down = self.down();
+                let packed = (1..5).all(|i| {
+                    matches!(self.get_cell(cell.0 - down.0 * i, cell.1 - down.1 * i).map(|other| other.state), Some(CellStates::Snow | CellStates::Ice))
+                });
+                if packed && self.rng.gen_range(0..50) == 0 {
+                    self.set(cell.0, cell.1, CellStates::Ice);
+                    return;
+                }
+                // light, drifts while falling
+                if self.rng.gen_range(0..2) == 0 {
+                    return;
+                }
+                if let (Some(gravity), true) = (self.gravity, self.pulled()) {
+                    let down = gravity.offset();
+                    let drift = self.rng.gen_range(-1..2);
+                    let target = turned(down, drift);
+                    let target = (cell.0 + target.0, cell.1 + target.1);
+                    if let Some(other) = self.get_cell(target.0, target.1) {
+                        if drift != 0 && other.state.hardness() > state.hardness() {
+                            self.swap(cell.0, cell.1, target);
+                            return;
+                        }
+                    }
+                }
+                powder_movement!();
+            }
+            CellStates::Cryo => {
+                for water in self.find_all_around(cell.0, cell.1, &CellStates::Water) {
+                    self.set(water.0, water.1, CellStates::Ice);
+                }
+                for vapor in self.find_all_around(cell.0, cell.1, &CellStates::Vapor) {
+                    self.set(vapor.0, vapor.1, CellStates::Snow);
+                }
+                for lava in self.find_all_around(cell.0, cell.1, &CellStates::Lava) {
+                    self.set(lava.0, lava.1, CellStates::Obsidian);
+                }
+                let fire = self.find_all_around_where(cell.0, cell.1, |state| matches!(state, CellStates::Fire(_)));
+                for fire in fire {
+                    self.set(fire.0, fire.1, CellStates::Air);
+                }
+                // burning cells go out, leaving what was burning
+                for burning in self.find_all_around(cell.0, cell.1, &CellStates::Burning) {
+                    let material = self.cells[burning.0 as usize][burning.1 as usize].matter();
+                    self.set(burning.0, burning.1, material);
+                }
+                if self.rng.gen_range(0..2000) == 0 {
+                    self.set(cell.0, cell.1, CellStates::Air);
+                    return;
+                }
+                liquid_movement!(cell.0, cell.1, state);
+            }
+            CellStates::Oil => {
+                liquid_movement!(cell.0, cell.1, state);
+            }
+            CellStates::Slime | CellStates::Honey => {
+                liquid_movement!(cell.0, cell.1, state);
+            }
+            CellStates::Mud => {
+                // dries back into soil away from water
+                if self.rng.gen_range(0..3000) == 0 && !self.is_around(cell.0, cell.1, &CellStates::Water) {
+                    self.set(cell.0, cell.1, CellStates::Soil);
+                    return;
+                }
+                liquid_movement!(cell.0, cell.1, state);
+            }
+            CellStates::Fuel => {
+                // volatile, evaporates from the surface
+                let down = self.down();
+                let surface = matches!(self.get_cell(cell.0 - down.0, cell.1 - down.1).map(|other| other.state), Some(CellStates::Air));
+                if surface && self.rng.gen_range(0..600) == 0 {
+                    self.set(cell.0, cell.1, CellStates::Methane);
+                    return;
+                }
+                liquid_movement!(cell.0, cell.1, state);
+            }
+            CellStates::Methane => {
+                gas_movement!(3);
+            }
+            CellStates::Smoke => {
+                // thins out, but only slowly when it can't spread
+                let open = self.is_around(cell.0, cell.1, &CellStates::Air);
+                if self.rng.gen_range(0..if open { 400 } else { 4000 }) == 0 {
+                    self.set(cell.0, cell.1, CellStates::Air);
+                    return;
+                }
+                gas_movement!(5);
+            }
+            CellStates::Base => {
+                liquid_movement!(cell.0, cell.1, state);
+            }
+            CellStates::ToxicGas => {
+                // wilts the plants it touches
+                for plant in self.find_all_around_where(cell.0, cell.1, |state| state.plant()) {
+                    if self.rng.gen_range(0..10) == 0 {
+                        self.set(plant.0, plant.1, CellStates::Ash);
+                    }
+                }
+                if self.rng.gen_range(0..1500) == 0 {
+                    self.set(cell.0, cell.1, CellStates::Air);
+                    return;
+                }
+                gas_movement!(3);
+            }
+            CellStates::Emitter => self.emit(cell),
+            CellStates::BlackHole => {
+                for loose in self.find_all_around_where(cell.0, cell.1, |state| state.loose()) {
+                    self.set(loose.0, loose.1, CellStates::Air);
+                }
+            }
+            CellStates::Repulsor => (),
+            CellStates::Fan(_) => (),
+            // swallows the cells moving into it, see `Grid::drained`
+            CellStates::Drain => (),
+            // learns its material in `Grid::touch`
+            CellStates::Clone => self.emit(cell),
+            CellStates::Metal => (),
+            CellStates::Power => (),
+            CellStates::Diode(_) | CellStates::And(_) | CellStates::Or(_) | CellStates::Not(_) | CellStates::Delay(_) => (),
+            CellStates::Switch(_) => (),
+            CellStates::Lamp(_) => (),
             CellStates::Ash => {
                 powder_movement!();
             }
@@ -277,43 +705,259 @@ impl Grid {
         }
     }
 
+    /// Turns gravity clockwise, going through zero gravity after pointing right.
+    pub fn rotate_gravity(&mut self) {
+        self.gravity = match self.gravity {
+            Some(Dir::Right) => None,
+            Some(dir) => Some(dir.rotated()),
+            None => Some(Dir::Down),
+        };
+    }
+
+    /// Where a falling cell moves this step, fields around win over gravity. In zero gravity it only drifts now and then.
+    pub fn fall(&mut self, cell: (i32, i32)) -> Option<(i32, i32)> {
+        if let Some(push) = self.push(cell) {
+            return Some(push);
+        }
+        let gravity = self.gravity;
+        match gravity {
+            Some(dir) if self.pulled() => Some(dir.offset()),
+            Some(_) => None,
+            None if self.rng.gen_range(0..8) == 0 => {
+                let dir = [Dir::Up, Dir::Right, Dir::Down, Dir::Left][self.rng.gen_range(0..4)];
+                Some(dir.offset())
+            }
+            None => None,
+        }
+    }
+
+    /// Whether gravity moves a falling cell in this step, weak gravity lets it hang for a while.
+    pub fn pulled(&mut self) -> bool {
+        self.gravity_strength >= FULL_GRAVITY || self.rng.gen_range(0..FULL_GRAVITY) < self.gravity_strength
+    }
+
+    /// Offset towards the ground for rules that need one in zero gravity as well, like packing snow or growing plants.
+    pub fn down(&self) -> (i32, i32) {
+        self.gravity.unwrap_or(Dir::Down).offset()
+    }
+
+    /// Where a rising flame goes this step, against gravity unless a field pushes it.
+    fn rise(&mut self, cell: (i32, i32)) -> (i32, i32) {
+        if let Some(push) = self.push(cell) {
+            return push;
+        }
+        match self.gravity {
+            Some(dir) => dir.opposite().offset(),
+            None => RING[self.rng.gen_range(0..8)],
+        }
+    }
+
+    /// Whether `state` could fall `depth` cells along `down`, starting with the cell at `from`.
+    fn drops(&self, from: (i32, i32), down: (i32, i32), state: CellStates, depth: i32) -> bool {
+        (0..depth).all(|i| match self.get_cell(from.0 + down.0 * i, from.1 + down.1 * i) {
+            Some(other) => other.matter().hardness() > state.hardness(),
+            None => false,
+        })
+    }
+
+    /// Whether the cell is next to a solid that doesn't move on its own.
+    fn touches_wall(&self, cell: (i32, i32)) -> bool {
+        !self.find_all_around_where(cell.0, cell.1, |state| state.hardness() == Hardness::Solid as i32 && !state.loose()).is_empty()
+    }
+
+    /// Swallows the cell when it moves into a drain, returns whether it did.
+    pub fn drained(&mut self, cell: (i32, i32), target: (i32, i32)) -> bool {
+        match self.get_cell(target.0, target.1) {
+            Some(other) if other.state == CellStates::Drain => {
+                self.set(cell.0, cell.1, CellStates::Air);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Teaches the clones around `pos` that have no material yet the material of the cell that just got there.
+    pub fn touch(&mut self, pos: (i32, i32)) {
+        let state = match self.get_cell(pos.0, pos.1) {
+            Some(cell) if cell.state.emittable() => cell.state,
+            _ => return,
+        };
+        for clone in self.find_all_around(pos.0, pos.1, &CellStates::Clone) {
+            let data = self.cells[clone.0 as usize][clone.1 as usize].data;
+            if data.material.is_none() {
+                self.set_data(clone.0, clone.1, CellData { material: Some(state), ..data });
+            }
+        }
+    }
+
+    /// Puts the material of the cell into a random empty neighbour.
+    fn emit(&mut self, cell: (i32, i32)) {
+        let material = match self.cells[cell.0 as usize][cell.1 as usize].data.material {
+            Some(material) => material,
+            None => return,
+        };
+        let empty = self.find_all_around(cell.0, cell.1, &CellStates::Air);
+        if let Some(target) = empty.choose(&mut self.rng) {
+            self.set(target.0, target.1, material);
+        }
+    }
+
+    /// Lets the flame at `cell` boil water, set off methane and set fire to its neighbours by their ignition chance.
+    fn spread_fire(&mut self, cell: (i32, i32)) {
+        for gas in self.find_all_around(cell.0, cell.1, &CellStates::Methane) {
+            self.set(gas.0, gas.1, CellStates::Spark);
+        }
+        for water in self.find_all_around(cell.0, cell.1, &CellStates::Water) {
+            self.set(water.0, water.1, CellStates::Vapor);
+        }
+        self.kindle(cell);
+    }
+
+    /// Sets fire to the neighbours of `cell` by their ignition chance.
+    fn kindle(&mut self, cell: (i32, i32)) {
+        for target in self.find_all_around_where(cell.0, cell.1, |state| state.burn().is_some()) {
+            let ignition = self.cells[target.0 as usize][target.1 as usize].state.burn().map_or(0, |burn| burn.ignition);
+            if self.rng.gen_range(0..100) < ignition {
+                self.ignite(target);
+            }
+        }
+    }
+
+    /// Sets the cell on fire when it burns, it then burns for as long as its fuel lasts.
+    ///
+    /// Every heat source goes through this, so gunpowder always burns first and only heaps of it explode.
+    pub fn ignite(&mut self, cell: (i32, i32)) {
+        let state = match self.get_cell(cell.0, cell.1) {
+            Some(other) => other.state,
+            None => return,
+        };
+        let burn = match state.burn() {
+            Some(burn) => burn,
+            None => return,
+        };
+        self.set(cell.0, cell.1, CellStates::Burning);
+        let data = self.cells[cell.0 as usize][cell.1 as usize].data;
+        self.set_data(cell.0, cell.1, CellData { material: Some(state), counter: burn.fuel, ..data });
+    }
+
+    /// Sends a flame of the given level up from `cell`, the hottest ones are smoky.
+    fn flame(&mut self, cell: (i32, i32), level: u8) {
+        let rand = self.rng.gen_range(-1..2);
+        let flame = turned(self.rise(cell), rand);
+        let flame = (cell.0 + flame.0, cell.1 + flame.1);
+        match self.get_cell(flame.0, flame.1) {
+            Some(other) if other.state.hardness() > Hardness::Plasma as i32 => {
+                let rising = if level >= 3 && self.rng.gen_range(0..3) == 0 { CellStates::Smoke } else { CellStates::Fire(level) };
+                self.set(flame.0, flame.1, rising);
+            }
+            _ => (),
+        }
+    }
+
+    /// Melts a frozen cell into `into`, quickly next to fire or lava and slowly when nothing cold is around.
+    ///
+    /// Returns whether the cell melted.
+    pub fn melt(&mut self, cell: (i32, i32), into: CellStates) -> bool {
+        let mut heat = 0;
+        let mut cold = false;
+        for i in -1..2 {
+            for j in -1..2 {
+                if let Some(other) = self.get_cell(cell.0 + i, cell.1 + j) {
+                    heat = heat.max(other.state.temperature());
+                    cold |= (i, j) != (0, 0) && other.state.temperature() < 0;
+                }
+            }
+        }
+        let chance = match heat {
+            500.. => 4,
+            1..=499 => 200,
+            // above the freezing point, but ice and snow keep each other cold
+            _ if !cold => 20000,
+            _ => return false,
+        };
+        if self.rng.gen_range(0..chance) == 0 {
+            self.set(cell.0, cell.1, into);
+            return true;
+        }
+        false
+    }
+
+    /// Sets the state of a cell.
+    ///
+    /// Stepping must change cells only through this, `set_data` and `swap`, so that `step_cell` can report the changes.
     pub fn set(&mut self, x: i32, y: i32, state: CellStates) {
         if !self.bounds(x, y) {
             return;
         }
+        let old = self.cells[x as usize][y as usize].state;
+        if old == state {
+            return;
+        }
+        if let Some(journal) = &mut self.journal {
+            journal.push(match old {
+                CellStates::Air => Change::Spawned { at: (x, y), state },
+                _ => Change::Transformed { at: (x, y), from: old, to: state },
+            });
+        }
         self.cells[x as usize][y as usize].state = state;
+        self.cells[x as usize][y as usize].data = CellData::new(self.rng.gen());
+        if state == CellStates::Emitter {
+            self.cells[x as usize][y as usize].data.material = Some(EMITTER_MATERIAL);
+        }
+        if state.rubble().is_some() {
+            self.cells[x as usize][y as usize].data.counter = self.body_id();
+        }
+        self.touch((x, y));
+    }
+
+    /// Replaces the data of a cell, keeping its state.
+    ///
+    /// Stepping must change cell data only through this, so that `step_cell` can report the changes.
+    pub fn set_data(&mut self, x: i32, y: i32, data: CellData) {
+        if !self.bounds(x, y) {
+            return;
+        }
+        let cell = &mut self.cells[x as usize][y as usize];
+        if cell.data == data {
+            return;
+        }
+        if let Some(journal) = &mut self.journal {
+            journal.push(Change::Updated { at: (x, y), state: cell.state });
+        }
+        cell.data = data;
     }
 
     pub fn swap(&mut self, x: i32, y: i32, other: (i32, i32)) {
         if !self.bounds(x, y) || !self.bounds(other.0, other.1) {
             return;
         }
-        let state = self.cells[x as usize][y as usize].state;
-        self.cells[x as usize][y as usize].state = self.cells[other.0 as usize][other.1 as usize].state;
-        self.cells[other.0 as usize][other.1 as usize].state = state;
+        if let Some(journal) = &mut self.journal {
+            if self.cells[x as usize][y as usize].state != self.cells[other.0 as usize][other.1 as usize].state {
+                journal.push(Change::Moved { from: (x, y), to: other });
+            }
+        }
+        // whole cells move, so that their charge goes with them
+        let cell = self.cells[x as usize][y as usize];
+        self.cells[x as usize][y as usize] = self.cells[other.0 as usize][other.1 as usize];
+        self.cells[other.0 as usize][other.1 as usize] = cell;
+        self.touch((x, y));
+        self.touch(other);
     }
 
     pub fn bounds(&self, x: i32, y: i32) -> bool {
         x >= 0 && x < self.size.0 && y >= 0 && y < self.size.1
     }
 
-    pub fn cell_unchecked(&mut self, x: i32, y: i32) -> &mut Cell {
-        &mut self.cells[x as usize][y as usize]
-    }
-
     pub fn is_around(&self, x: i32, y: i32, state: &CellStates) -> bool {
         for i in -1..2 {
             for j in -1..2 {
                 if i == 0 && j == 0 {
                     continue;
                 }
-                match self.get_cell(x + i, y + j) {
-                    Some(cell) => {
-                        if cell.state == *state {
-                            return true
-                        }
+                if let Some(cell) = self.get_cell(x + i, y + j) {
+                    if cell.state == *state {
+                        return true
                     }
-                    None => ()
                 }
             }
         }
@@ -327,13 +971,27 @@ impl Grid {
                 if i == 0 && j == 0 {
                     continue;
                 }
-                match self.get_cell(x + i, y + j) {
-                    Some(cell) => {
-                        if cell.state == *state {
-                            result.push((x+i, y+j));
-                        }
+                if let Some(cell) = self.get_cell(x + i, y + j) {
+                    if cell.state == *state {
+                        result.push((x+i, y+j));
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    pub fn find_all_around_where(&self, x: i32, y: i32, f: impl Fn(&CellStates) -> bool) -> Vec<(i32, i32)> {
+        let mut result = Vec::new();
+        for i in -1..2 {
+            for j in -1..2 {
+                if i == 0 && j == 0 {
+                    continue;
+                }
+                if let Some(cell) = self.get_cell(x + i, y + j) {
+                    if f(&cell.state) {
+                        result.push((x+i, y+j));
                     }
-                    None => ()
                 }
             }
         }
@@ -347,16 +1005,305 @@ impl Grid {
                 if i == 0 && j == 0 {
                     continue;
                 }
-                match self.get_cell(x + i, y + j) {
-                    Some(cell) => {
-                        if cell.state.hardness().cmp(&state) == cmp {
-                            result.push((x+i, y+j));
-                        }
+                if let Some(cell) = self.get_cell(x + i, y + j) {
+                    if cell.state.hardness().cmp(&state) == cmp {
+                        result.push((x+i, y+j));
                     }
-                    None => ()
                 }
             }
         }
         result
     }
-}
\ No newline at end of file
+}
+
+/// Neighbour offsets going clockwise from up.
+const RING: [(i32, i32); 8] = [(0, -1), (1, -1), (1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1)];
+
+/// Turns a neighbour offset clockwise by `eighths` of a full turn, or anticlockwise when negative.
+pub fn turned(offset: (i32, i32), eighths: i32) -> (i32, i32) {
+    let idx = RING.iter().position(|other| *other == offset).unwrap_or(4) as i32;
+    RING[(idx + eighths).rem_euclid(8) as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    /// Small empty grid with a fixed seed.
+    fn grid() -> Grid {
+        let mut grid = Grid::new((5, 5));
+        grid.seed(7);
+        grid
+    }
+
+    #[test]
+    fn sand_falls() {
+        let mut grid = grid();
+        grid.set(2, 1, CellStates::Sand);
+        assert_eq!(grid.step_cell(2, 1), vec![Change::Moved { from: (2, 1), to: (2, 2) }]);
+        assert_eq!(grid.cells[2][2].state, CellStates::Sand);
+        assert_eq!(grid.cells[2][1].state, CellStates::Air);
+    }
+
+    #[test]
+    fn sand_falls_with_gravity() {
+        let mut grid = grid();
+        grid.gravity = Some(Dir::Right);
+        grid.set(2, 2, CellStates::Sand);
+        assert_eq!(grid.step_cell(2, 2), vec![Change::Moved { from: (2, 2), to: (3, 2) }]);
+    }
+
+    #[test]
+    fn weak_gravity_falls_slower() {
+        let mut grid = grid();
+        grid.gravity_strength = 1;
+        grid.set(2, 0, CellStates::Sand);
+        for _ in 0..10 {
+            grid.step_cell(2, 0);
+        }
+        assert_eq!(grid.cells[2][0].state, CellStates::Sand);
+    }
+
+    #[test]
+    fn sand_rests_on_the_floor() {
+        let mut grid = grid();
+        grid.set(2, 4, CellStates::Sand);
+        assert_eq!(grid.step_cell(2, 4), vec![]);
+    }
+
+    #[test]
+    fn water_spreads() {
+        let mut grid = grid();
+        grid.set(2, 4, CellStates::Water);
+        let changes = grid.step_cell(2, 4);
+        match changes.as_slice() {
+            [Change::Moved { from: (2, 4), to }] => assert!(*to == (1, 4) || *to == (3, 4), "moved to {:?}", to),
+            _ => panic!("unexpected changes {:?}", changes),
+        }
+    }
+
+    #[test]
+    fn lava_moves_in_a_third_of_steps() {
+        let mut grid = grid();
+        let mut moved = 0;
+        for _ in 0..300 {
+            grid.set(2, 0, CellStates::Lava);
+            moved += grid.step_cell(2, 0).len();
+            for x in 0..5 {
+                grid.set(x, 1, CellStates::Air);
+            }
+        }
+        assert!((70..130).contains(&moved), "moved {moved} times");
+    }
+
+    #[test]
+    fn fire_spawns_flames_above() {
+        let mut grid = grid();
+        let mut spawned = Vec::new();
+        for _ in 0..20 {
+            grid.set(2, 3, CellStates::Fire(2));
+            for change in grid.step_cell(2, 3) {
+                if let Change::Spawned { at, state } = change {
+                    spawned.push((at, state));
+                }
+            }
+            for x in 0..5 {
+                grid.set(x, 2, CellStates::Air);
+            }
+        }
+        assert!(!spawned.is_empty());
+        for (at, state) in spawned {
+            assert_eq!(at.1, 2);
+            assert!(matches!(state, CellStates::Fire(2) | CellStates::Smoke), "spawned {state}");
+        }
+    }
+
+    #[test]
+    fn burning_reports_its_fuel() {
+        let mut grid = grid();
+        grid.set(2, 2, CellStates::Wood);
+        grid.ignite((2, 2));
+        let fuel = grid.cells[2][2].data.counter;
+        let changes = grid.step_cell(2, 2);
+        assert!(changes.contains(&Change::Updated { at: (2, 2), state: grid.cells[2][2].state }));
+        assert_eq!(grid.cells[2][2].data.counter, fuel - 1);
+    }
+
+    #[test]
+    fn drain_swallows_cells_moving_in() {
+        let mut grid = grid();
+        grid.set(2, 4, CellStates::Drain);
+        grid.set(2, 3, CellStates::Sand);
+        assert_eq!(grid.step_cell(2, 3), vec![Change::Transformed { at: (2, 3), from: CellStates::Sand, to: CellStates::Air }]);
+        assert_eq!(grid.cells[2][4].state, CellStates::Drain);
+    }
+
+    #[test]
+    fn drain_keeps_resting_neighbours() {
+        let mut grid = grid();
+        grid.set(2, 4, CellStates::Drain);
+        grid.set(1, 4, CellStates::Sand);
+        for _ in 0..20 {
+            grid.step_cell(2, 4);
+            grid.step_cell(1, 4);
+        }
+        assert_eq!(grid.cells[1][4].state, CellStates::Sand);
+    }
+
+    #[test]
+    fn clone_learns_the_first_arrival() {
+        let mut grid = grid();
+        grid.set(2, 4, CellStates::Clone);
+        grid.set(2, 2, CellStates::Sand);
+        grid.set(0, 0, CellStates::Water);
+        grid.step_cell(2, 2);
+        assert_eq!(grid.cells[2][4].data.material, Some(CellStates::Sand));
+        grid.set(1, 3, CellStates::Water);
+        assert_eq!(grid.cells[2][4].data.material, Some(CellStates::Sand));
+        let changes = grid.step_cell(2, 4);
+        assert!(matches!(changes.as_slice(), [Change::Spawned { state: CellStates::Sand, .. }]), "{:?}", changes);
+    }
+
+    #[test]
+    fn emitter_from_set_emits() {
+        let mut grid = grid();
+        grid.set(2, 2, CellStates::Emitter);
+        let changes = grid.step_cell(2, 2);
+        assert!(matches!(changes.as_slice(), [Change::Spawned { state: EMITTER_MATERIAL, .. }]), "{:?}", changes);
+    }
+
+    #[test]
+    fn burning_oil_flows() {
+        let mut grid = grid();
+        grid.set(2, 2, CellStates::Oil);
+        grid.ignite((2, 2));
+        assert_eq!(grid.cells[2][2].state, CellStates::Burning);
+        for _ in 0..10 {
+            grid.step_cell(2, 2);
+            if grid.cells[2][2].state == CellStates::Air {
+                break;
+            }
+        }
+        assert_eq!(grid.cells[2][2].state, CellStates::Air);
+        assert_eq!(grid.cells[2][3].state, CellStates::Burning);
+    }
+
+    #[test]
+    fn burning_oil_floats() {
+        let mut grid = grid();
+        grid.set(2, 2, CellStates::Oil);
+        grid.ignite((2, 2));
+        let burning = grid.cells[2][2];
+        assert_eq!(burning.matter(), CellStates::Oil);
+        assert!(!burning.matter().sinks_into(&CellStates::Water));
+        assert!(!burning.matter().sinks_into(&CellStates::Oil));
+        for seed in 0..20 {
+            // on water, it never trades places with the denser water
+            let mut grid = Grid::new((5, 5));
+            grid.seed(seed);
+            for x in 0..5 {
+                grid.set(x, 3, CellStates::Water);
+                grid.set(x, 4, CellStates::Water);
+            }
+            grid.set(2, 2, CellStates::Oil);
+            grid.ignite((2, 2));
+            for _ in 0..20 {
+                for x in 0..5 {
+                    if grid.cells[x][2].state == CellStates::Burning {
+                        grid.step_cell(x as i32, 2);
+                    }
+                }
+                assert!(grid.cells.iter().all(|column| column[2].state != CellStates::Water), "burning oil sank into water");
+            }
+            // on oil, in a shaft where only sinking could move it down, before the oil below catches fire,
+            // which the burning cell is told apart from by its fuel
+            let mut grid = Grid::new((5, 5));
+            grid.seed(seed);
+            for y in 2..5 {
+                grid.set(1, y, CellStates::Stone);
+                grid.set(3, y, CellStates::Stone);
+            }
+            grid.set(2, 3, CellStates::Oil);
+            grid.set(2, 4, CellStates::Oil);
+            grid.set(2, 2, CellStates::Oil);
+            grid.ignite((2, 2));
+            grid.set_data(2, 2, CellData { counter: 1000, ..grid.cells[2][2].data });
+            grid.step_cell(2, 2);
+            assert!(grid.cells[2][3].data.counter < 900, "burning oil sank into oil");
+        }
+    }
+
+    #[test]
+    fn lava_ignites_gunpowder() {
+        let mut grid = grid();
+        grid.set(2, 4, CellStates::Lava);
+        grid.set(3, 4, CellStates::Gunpowder);
+        let changes = grid.step_cell(2, 4);
+        assert!(changes.contains(&Change::Transformed { at: (3, 4), from: CellStates::Gunpowder, to: CellStates::Burning }), "{:?}", changes);
+    }
+
+    #[test]
+    fn neutralisation_sets_fire() {
+        let mut grid = grid();
+        grid.set(1, 4, CellStates::Base);
+        grid.set(2, 4, CellStates::Acid);
+        grid.set(3, 4, CellStates::Oil);
+        grid.step_cell(2, 4);
+        assert_eq!(grid.cells[1][4].state, CellStates::Salt);
+        assert!(matches!(grid.cells[2][4].state, CellStates::Water | CellStates::Vapor));
+        assert_eq!(grid.cells[3][4].state, CellStates::Burning);
+    }
+
+    #[test]
+    fn levelling_moves_the_cell_data() {
+        let mut grid = Grid::new((5, 6));
+        grid.seed(7);
+        for y in 0..5 {
+            grid.set(1, y, CellStates::Wall);
+        }
+        let liquid: Vec<(i32, i32)> = (1..6).map(|y| (0, y)).chain((1..5).map(|x| (x, 5))).collect();
+        for pos in liquid.iter() {
+            grid.set(pos.0, pos.1, CellStates::Acid);
+            let data = grid.cells[pos.0 as usize][pos.1 as usize].data;
+            grid.set_data(pos.0, pos.1, CellData { counter: 3, ..data });
+        }
+        grid.pressure_tick();
+        assert_eq!(grid.cells[0][1].state, CellStates::Air);
+        let moved: Vec<&Cell> = (2..5).map(|x| &grid.cells[x][4]).filter(|cell| cell.state == CellStates::Acid).collect();
+        assert_eq!(moved.len(), 1);
+        assert_eq!(moved[0].data.counter, 3);
+    }
+
+    #[test]
+    fn crates_from_set_are_separate_bodies() {
+        let mut grid = grid();
+        grid.set(1, 4, CellStates::Crate);
+        grid.set(2, 4, CellStates::Crate);
+        assert_ne!(grid.cells[1][4].data.counter, grid.cells[2][4].data.counter);
+        let stroke = grid.body_id();
+        assert!(stroke > grid.cells[2][4].data.counter);
+    }
+
+    #[test]
+    fn pressure_leaves_the_counter_alone() {
+        let mut grid = grid();
+        for x in 0..5 {
+            for y in 2..5 {
+                grid.set(x, y, CellStates::Water);
+            }
+        }
+        let data = grid.cells[2][4].data;
+        grid.set_data(2, 4, CellData { counter: 7, ..data });
+        grid.pressure_tick();
+        assert_eq!(grid.cells[2][4].data.counter, 7);
+        assert_eq!(grid.pressure[2][4], 2);
+        assert_eq!(grid.pressure[2][2], 0);
+    }
+
+    #[test]
+    fn out_of_bounds_reports_nothing() {
+        let mut grid = grid();
+        assert_eq!(grid.step_cell(-1, 2), vec![]);
+        assert_eq!(grid.step_cell(2, 5), vec![]);
+    }
+}
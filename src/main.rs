@@ -1,8 +1,3 @@
-use std::{
-    ffi::{CStr, OsStr},
-    io::Empty, collections::HashMap, os::windows,
-};
-
 /*
 
     KDO SE TU SAKRA HRABAL V THEMES - Danecek
@@ -16,12 +11,40 @@ use grid::*;
 
 mod window;
 
+mod electricity;
+use electricity::*;
+
+mod scenes;
+
+mod plants;
+
+mod agents;
+use agents::*;
+
+mod fields;
+use fields::*;
+
+mod pressure;
+
+mod structure;
+
+mod bodies;
+use bodies::*;
+
+mod epidemic;
+use epidemic::*;
+
 
 use raylib::prelude::*;
 
 const WINDOW_INIT_SIZE: (i32, i32) = (640, 480);
 const GRID_INIT_SIZE: (i32, i32) = (192, 144);
 const ITERATIONS: i32 = 15000;
+const TICKS_PER_SECOND: i32 = 60;
+const MAX_ITERATIONS: i32 = 200000;
+const MAX_TPS: i32 = 240;
+/// Share of a 60 FPS frame the auto speed mode may spend stepping the grid.
+const AUTO_FRAME_BUDGET: f32 = 0.5;
 
 fn main() {
     let (mut rl, thread) = raylib::init()
@@ -37,7 +60,7 @@ fn main() {
     rl.set_target_fps(60);
 
     while !rl.window_should_close() {
-        let sys_time = std::time::SystemTime::now();
+        let frame_time = rl.get_frame_time();
         if rl.is_window_resized() {
             game.resize_screen((rl.get_screen_width(), rl.get_screen_height()));
         }
@@ -46,7 +69,7 @@ fn main() {
         match game.state() {
             GameState::Running => {
 
-                game.update();
+                game.update(frame_time);
         
                 d.clear_background(Color::WHITE);
                 game.draw(&mut d);
@@ -55,6 +78,9 @@ fn main() {
                 d.draw_fps(10, game.screen.1 - 30);
             },
             GameState::Paused => {
+                // paused games still advance when single stepping
+                game.update(frame_time);
+
                 d.clear_background(Color::WHITE);
                 game.draw(&mut d);
             }
@@ -69,33 +95,62 @@ fn main() {
                 }
             }
         }
-
-        // print runtime usage
-        // let elapsed = sys_time.elapsed().unwrap();
-        // d.draw_text(&format!("{}ms", elapsed.as_millis()), 10, 30, 20, Color::BLACK);
     }
 }
 
 struct Game {
     grid: Grid,
+    agents: Agents,
+    bodies: Bodies,
     settings: Settings,
     screen: (i32, i32),
     saved: Vec<Vec<Vec<Cell>>>,
-    /// 
+    /// Cells before each stroke, newest last, for undo.
     backup: Vec<Vec<Vec<Cell>>>,
     window: Option<String>,
+    /// Iterations requested by the step buttons, done on the next update.
+    pending_steps: i32,
+    /// Fractional iterations left over from slow motion.
+    step_budget: f32,
+    /// Fractional ticks left over from the previous frames.
+    tick_budget: f32,
+    /// Steps done since `sps_timer` was reset.
+    sps_counter: i32,
+    sps_timer: f32,
+    /// Measured simulation speed in steps per second.
+    steps_per_second: i32,
+    /// Cell shown in the inspector panel.
+    inspected: Option<(i32, i32)>,
+    /// Changes done by the last step forced from the inspector.
+    inspector_changes: Vec<Change>,
 }
 
 struct Settings {
     iterations: i32,
     pause: bool,
     brush: Brush,
+    /// Number of iterations done by a single press of the step button.
+    step_size: i32,
+    /// First cell type shown in the right panel.
+    palette_scroll: i32,
+    /// Slow-motion factor in range 0..=1, applied quadratically to `iterations`.
+    speed: f32,
+    /// Target number of ticks per second, each tick does `iterations` steps.
+    tps: i32,
+    /// Adjust `iterations` automatically to hold 60 FPS.
+    auto_speed: bool,
+    /// Show the graph of the plague.
+    census: bool,
 }
 
 struct Brush {
     size: i32,
     state: Option<CellStates>,
     override_state: bool,
+    /// Material given to placed emitters, the last one picked in the palette.
+    material: CellStates,
+    /// Body id of the current stroke, crates or boulders painted in one stroke form one body.
+    stroke: i32,
 }
 
 impl Game {
@@ -104,6 +159,8 @@ impl Game {
         grid.recalculate_dim(screen);
         Game {
             grid,
+            agents: Agents::new(),
+            bodies: Bodies::new(),
             screen,
             settings: Settings {
                 iterations: ITERATIONS,
@@ -112,11 +169,27 @@ impl Game {
                     size: 3,
                     state: None,
                     override_state: false,
+                    material: EMITTER_MATERIAL,
+                    stroke: 0,
                 },
+                step_size: 100,
+                palette_scroll: 0,
+                speed: 1.,
+                tps: TICKS_PER_SECOND,
+                auto_speed: false,
+                census: false,
             },
             saved: Vec::new(),
             window: None,
             backup: Vec::new(),
+            pending_steps: 0,
+            step_budget: 0.,
+            tick_budget: 0.,
+            sps_counter: 0,
+            sps_timer: 0.,
+            steps_per_second: 0,
+            inspected: None,
+            inspector_changes: Vec::new(),
         }
     }
 
@@ -136,12 +209,67 @@ impl Game {
         self.screen = screen;
     }
 
-    pub fn update(&mut self) {
-        if !self.settings.pause {
-            for _ in 0..self.settings.iterations {
+    /// Advances the simulation by the time passed since the last frame.
+    pub fn update(&mut self, frame_time: f32) {
+        self.sps_timer += frame_time;
+        if self.sps_timer >= 1. {
+            self.steps_per_second = (self.sps_counter as f32 / self.sps_timer) as i32;
+            self.sps_counter = 0;
+            self.sps_timer = 0.;
+        }
+        if self.settings.pause {
+            for _ in 0..self.pending_steps {
                 self.grid.step();
             }
+            self.agents.update(&mut self.grid, self.pending_steps);
+            self.bodies.update(&mut self.grid, self.pending_steps);
+            self.sps_counter += self.pending_steps;
+            self.pending_steps = 0;
+            return;
         }
+        // never catch up more than a few ticks, otherwise a slow frame makes the next one even slower
+        self.tick_budget = (self.tick_budget + frame_time * self.settings.tps as f32).min(4.);
+        let ticks = self.tick_budget.floor();
+        self.tick_budget -= ticks;
+        let start = std::time::Instant::now();
+        for _ in 0..ticks as i32 {
+            // squared so that the slow end of the slider is usable
+            self.step_budget += self.settings.iterations as f32 * self.settings.speed.powi(2);
+            let iterations = self.step_budget.floor();
+            self.step_budget -= iterations;
+            for _ in 0..iterations as i32 {
+                self.grid.step();
+            }
+            self.agents.update(&mut self.grid, iterations as i32);
+            self.bodies.update(&mut self.grid, iterations as i32);
+            self.sps_counter += iterations as i32;
+        }
+        if self.settings.auto_speed && ticks > 0. {
+            let elapsed = start.elapsed().as_secs_f32().max(f32::EPSILON);
+            let ratio = (AUTO_FRAME_BUDGET / 60. / elapsed).clamp(0.9, 1.1);
+            self.set_iterations((self.settings.iterations as f32 * ratio) as i32);
+        }
+    }
+
+    /// Sets the number of steps done every tick.
+    pub fn set_iterations(&mut self, iterations: i32) {
+        self.settings.iterations = iterations.clamp(1, MAX_ITERATIONS);
+    }
+
+    /// Sets the target number of ticks per second, independent of the rendering FPS.
+    pub fn set_tps(&mut self, tps: i32) {
+        self.settings.tps = tps.clamp(1, MAX_TPS);
+    }
+
+    /// Measured simulation speed, updated once a second.
+    pub fn steps_per_second(&self) -> i32 {
+        self.steps_per_second
+    }
+
+    /// Pauses the game and requests `iterations` steps to be done on the next update.
+    pub fn step(&mut self, iterations: i32) {
+        self.settings.pause = true;
+        self.pending_steps += iterations;
     }
 
     /// Draws the game.
@@ -150,13 +278,17 @@ impl Game {
     pub fn draw(&mut self, d: &mut RaylibDrawHandle) {
         let mouse = d.get_mouse_position();
         let mouse_cell = (
-            (mouse.x as f64 / self.grid.cell_dim.0 as f64
-                - self.grid.dim.0 as f64 / self.grid.cell_dim.0 as f64) as i32,
-            (mouse.y as f64 / self.grid.cell_dim.1 as f64
-                - self.grid.dim.1 as f64 / self.grid.cell_dim.1 as f64) as i32,
+            (mouse.x as f64 / self.grid.cell_dim.0
+                - self.grid.dim.0 / self.grid.cell_dim.0) as i32,
+            (mouse.y as f64 / self.grid.cell_dim.1
+                - self.grid.dim.1 / self.grid.cell_dim.1) as i32,
         );
+        // the inspector and the graph are drawn over the grid, clicks on them should not reach the cells
+        let over_inspector = (self.inspected.is_some() && self.inspector_bounds().check_collision_point_rec(mouse))
+            || (self.settings.census && self.census_bounds().check_collision_point_rec(mouse));
         // check bounds
         if d.is_mouse_button_down(MouseButton::MOUSE_RIGHT_BUTTON)
+        && !over_inspector
         && mouse_cell.0 >= 0
         && mouse_cell.0 < self.grid.size.0
         && mouse_cell.1 >= 0
@@ -177,13 +309,13 @@ impl Game {
                     && mouse_cell.1 + y >= 0
                 {
                     self.grid.cells[(mouse_cell.0 + x) as usize]
-                        [(mouse_cell.1 + y) as usize]
-                        .state = CellStates::Air;
+                        [(mouse_cell.1 + y) as usize] = Cell::new();
                 }
             }
         }
     }
         if d.is_mouse_button_down(MouseButton::MOUSE_LEFT_BUTTON)
+            && !over_inspector
             && mouse_cell.0 >= 0
             && mouse_cell.0 < self.grid.size.0
             && mouse_cell.1 >= 0
@@ -193,6 +325,7 @@ impl Game {
             // save backup
             if d.is_mouse_button_pressed(MouseButton::MOUSE_LEFT_BUTTON) {
                 self.backup();
+                self.settings.brush.stroke = self.grid.body_id();
             }
             match self.settings.brush.state {
                 Some(state) => {
@@ -204,21 +337,56 @@ impl Game {
                                 && mouse_cell.1 + y < self.grid.size.1
                                 && mouse_cell.0 + x >= 0
                                 && mouse_cell.1 + y >= 0
+                                && (state == CellStates::Air
+                                    || self.settings.brush.override_state
+                                    || self.grid.cells[(mouse_cell.0 + x) as usize][(mouse_cell.1 + y) as usize]
+                                        .state
+                                        .hardness()
+                                        >= state.hardness())
                             {
-                                if state == CellStates::Air || self.settings.brush.override_state || self.grid.cells[(mouse_cell.0 + x) as usize]
-                                    [(mouse_cell.1 + y) as usize]
-                                    .state.hardness() >= state.hardness() {
-                                    self.grid.cells[(mouse_cell.0 + x) as usize]
-                                        [(mouse_cell.1 + y) as usize]
-                                        .state = state;
-                                    }
+                                let cell = &mut self.grid.cells[(mouse_cell.0 + x) as usize]
+                                    [(mouse_cell.1 + y) as usize];
+                                cell.state = state;
+                                cell.data = CellData::new(rand::random());
+                                // a cell painted over a live wire starts without its charge and gate history
+                                cell.charge = Charge::Idle;
+                                cell.signal = 0;
+                                if state == CellStates::Emitter {
+                                    cell.data.material = Some(self.settings.brush.material);
+                                }
+                                if state.rubble().is_some() {
+                                    cell.data.counter = self.settings.brush.stroke;
+                                }
+                                self.grid.touch((mouse_cell.0 + x, mouse_cell.1 + y));
+                            }
+                        }
+                    }
+                }
+                None => {
+                    if d.is_mouse_button_pressed(MouseButton::MOUSE_LEFT_BUTTON) {
+                        match self.grid.cells[mouse_cell.0 as usize][mouse_cell.1 as usize].state {
+                            CellStates::Switch(on) => self.grid.set(mouse_cell.0, mouse_cell.1, CellStates::Switch(!on)),
+                            _ => {
+                                self.inspected = Some(mouse_cell);
+                                self.inspector_changes.clear();
                             }
                         }
                     }
                 }
-                None => (),
             }
         }
+        if d.is_key_pressed(KeyboardKey::KEY_R) {
+            self.settings.brush.state = self.settings.brush.state.map(|state| state.rotated());
+        }
+        if d.is_key_pressed(KeyboardKey::KEY_G) {
+            self.grid.rotate_gravity();
+        }
+        if d.is_key_pressed(KeyboardKey::KEY_C) {
+            self.grid.collapse = !self.grid.collapse;
+        }
+        if d.is_key_pressed(KeyboardKey::KEY_P) {
+            self.settings.census = !self.settings.census;
+        }
         macro_rules! cstr {
             ($s:expr) => {
                 Some(std::ffi::CString::new($s).unwrap().as_c_str())
@@ -262,9 +430,10 @@ impl Game {
         ) {
             for x in 0..self.grid.size.0 {
                 for y in 0..self.grid.size.1 {
-                    self.grid.cells[x as usize][y as usize].state = CellStates::Air;
+                    self.grid.cells[x as usize][y as usize] = Cell::new();
                 }
             }
+            self.grid.clear_census();
         }
         if d.gui_button(
             Rectangle::new(
@@ -277,10 +446,10 @@ impl Game {
         ) {
             self.saved.push(self.grid.cells.clone());
         }
-        let load_txt = if self.saved.len() > 0 {
+        let load_txt = if !self.saved.is_empty() {
             format!("load ({})", self.saved.len())
         } else {
-            format!("no saves")
+            "no saves".to_string()
         };
         if d.gui_button(
             Rectangle::new(
@@ -294,6 +463,7 @@ impl Game {
             if let Some(grid) = self.saved.pop() {
                 self.backup();
                 self.grid.cells = grid;
+                self.bodies.forget();
             }
         }
         // bounds, text_left, text_right, value, min_value, max_value
@@ -336,15 +506,202 @@ impl Game {
         ) {
             self.undo();
         }
+        self.settings.step_size = d.gui_slider_bar(
+            Rectangle::new(
+                button_padding.0 as f32 + 33.,
+                button_height as f32 + button_dims.1 as f32 * 7.35,
+                button_dims.0 as f32 - 33.,
+                button_dims.1 as f32,
+            ),
+            cstr!("Steps"),
+            None,
+            self.settings.step_size as f32,
+            1.,
+            1000.,
+        ) as i32;
+        let step_txt = format!("Step {}", self.settings.step_size);
+        if d.gui_button(
+            Rectangle::new(
+                button_padding.0 as f32,
+                button_height as f32 + button_dims.1 as f32 * 8.4,
+                button_dims.0 as f32,
+                button_dims.1 as f32,
+            ),
+            cstr!(step_txt.as_str()),
+        ) {
+            self.step(self.settings.step_size);
+        }
+        if d.gui_button(
+            Rectangle::new(
+                button_padding.0 as f32,
+                button_height as f32 + button_dims.1 as f32 * 9.45,
+                button_dims.0 as f32,
+                button_dims.1 as f32,
+            ),
+            cstr!("Step gen"),
+        ) {
+            self.step(self.grid.generation());
+        }
+        self.settings.speed = d.gui_slider_bar(
+            Rectangle::new(
+                button_padding.0 as f32 + 33.,
+                button_height as f32 + button_dims.1 as f32 * 10.5,
+                button_dims.0 as f32 - 33.,
+                button_dims.1 as f32,
+            ),
+            cstr!("Speed"),
+            None,
+            self.settings.speed,
+            0.,
+            1.,
+        );
+        let iterations = d.gui_slider_bar(
+            Rectangle::new(
+                button_padding.0 as f32 + 33.,
+                button_height as f32 + button_dims.1 as f32 * 11.55,
+                button_dims.0 as f32 - 33.,
+                button_dims.1 as f32,
+            ),
+            cstr!("Iter."),
+            None,
+            self.settings.iterations as f32,
+            1.,
+            MAX_ITERATIONS as f32,
+        ) as i32;
+        self.set_iterations(iterations);
+        let tps = d.gui_slider_bar(
+            Rectangle::new(
+                button_padding.0 as f32 + 33.,
+                button_height as f32 + button_dims.1 as f32 * 12.6,
+                button_dims.0 as f32 - 33.,
+                button_dims.1 as f32,
+            ),
+            cstr!("TPS"),
+            None,
+            self.settings.tps as f32,
+            1.,
+            MAX_TPS as f32,
+        ) as i32;
+        self.set_tps(tps);
+        let auto_text = match self.settings.auto_speed {
+            true => "Auto: On",
+            false => "Auto: Off"
+        };
+        if d.gui_button(
+            Rectangle::new(
+                button_padding.0 as f32,
+                button_height as f32 + button_dims.1 as f32 * 13.65,
+                button_dims.0 as f32,
+                button_dims.1 as f32,
+            ),
+            cstr!(auto_text),
+        ) {
+            self.settings.auto_speed = !self.settings.auto_speed;
+        }
+        d.draw_text(
+            &format!("{} steps/s", self.steps_per_second()),
+            button_padding.0,
+            (button_height as f32 + button_dims.1 as f32 * 14.7) as i32,
+            10,
+            Color::BLACK,
+        );
+        // toggled by the C key, shown so that falling walls don't come as a surprise
+        let collapse_txt = match self.grid.collapse {
+            true => "Collapse: On (C)",
+            false => "Collapse: Off (C)",
+        };
+        d.draw_text(
+            collapse_txt,
+            button_padding.0,
+            (button_height as f32 + button_dims.1 as f32 * 14.7) as i32 + 12,
+            10,
+            match self.grid.collapse {
+                true => Color::MAROON,
+                false => Color::DARKGRAY,
+            },
+        );
+        if d.gui_button(
+            Rectangle::new(
+                button_padding.0 as f32,
+                button_height as f32 + button_dims.1 as f32 * 15.75,
+                button_dims.0 as f32 / 2. - 1.,
+                button_dims.1 as f32,
+            ),
+            cstr!("Scenes"),
+        ) {
+            self.window = Some("Scenes".to_string());
+        }
+        let gravity_txt = match self.grid.gravity {
+            Some(dir) => format!("G: {}", dir.arrow()),
+            None => "G: 0".to_string(),
+        };
+        if d.gui_button(
+            Rectangle::new(
+                button_padding.0 as f32 + button_dims.0 as f32 / 2. + 1.,
+                button_height as f32 + button_dims.1 as f32 * 15.75,
+                button_dims.0 as f32 / 2. - 1.,
+                button_dims.1 as f32,
+            ),
+            cstr!(gravity_txt),
+        ) {
+            self.grid.rotate_gravity();
+        }
+        self.grid.gravity_strength = d.gui_slider_bar(
+            Rectangle::new(
+                button_padding.0 as f32 + 33.,
+                button_height as f32 + button_dims.1 as f32 * 16.8,
+                button_dims.0 as f32 - 33.,
+                button_dims.1 as f32,
+            ),
+            cstr!("Grav."),
+            None,
+            self.grid.gravity_strength as f32,
+            1.,
+            FULL_GRAVITY as f32,
+        ) as i32;
+        if d.gui_button(
+            Rectangle::new(
+                button_padding.0 as f32,
+                button_height as f32 + button_dims.1 as f32 * 17.85,
+                button_dims.0 as f32 / 2. - 1.,
+                button_dims.1 as f32,
+            ),
+            cstr!("Help"),
+        ) {
+            self.window = Some("Help".to_string());
+        }
+        if d.gui_button(
+            Rectangle::new(
+                button_padding.0 as f32 + button_dims.0 as f32 / 2. + 1.,
+                button_height as f32 + button_dims.1 as f32 * 17.85,
+                button_dims.0 as f32 / 2. - 1.,
+                button_dims.1 as f32,
+            ),
+            cstr!("About"),
+        ) {
+            self.window = Some("About".to_string());
+        }
         // right side
+        // scroll the list when it doesn't fit on the screen
+        let list = CellStates::list();
+        let visible = (((self.screen.1 - button_height) as f32 / (button_dims.1 as f32 * 1.05)) as usize).max(1);
+        if mouse.x as f64 > self.grid.dim.0 + self.grid.dim.2 {
+            self.settings.palette_scroll -= d.get_mouse_wheel_move() as i32;
+        }
+        self.settings.palette_scroll = self.settings.palette_scroll.clamp(0, list.len().saturating_sub(visible) as i32);
         // draw button for each cell state for brush
-        for (idx, state) in CellStates::list().iter().enumerate() {
-            let button_txt = if self.settings.brush.state.is_some()
-                && self.settings.brush.state.unwrap() == *state
-            {
-                format!("[X]-{state}")
-            } else {
-                format!("[ ]-{state}")
+        for (idx, state) in list.iter().enumerate().skip(self.settings.palette_scroll as usize).take(visible) {
+            let idx = idx - self.settings.palette_scroll as usize;
+            let name = match state {
+                CellStates::Emitter => format!("{state} ({})", self.settings.brush.material),
+                _ => state.to_string(),
+            };
+            let button_txt = match self.settings.brush.state {
+                Some(brush) if brush.same_kind(state) => match brush.dir() {
+                    Some(dir) => format!("[X]-{name} {}", dir.arrow()),
+                    None => format!("[X]-{name}"),
+                },
+                _ => format!("[ ]-{name}"),
             };
             let button_pos = (
                 self.grid.dim.2 as f32 + self.grid.dim.0 as f32 * 1.1,
@@ -359,26 +716,22 @@ impl Game {
                 ),
                 cstr!(button_txt),
             ) {
-                match self.settings.brush.state {
-                    Some(s) => {
-                        if s == *state {
-                            self.settings.brush.state = None;
-                        } else {
-                            self.settings.brush.state = Some(*state);
-                        }
-                    }
-                    None => {
-                        self.settings.brush.state = Some(*state);
-                    }
+                if state.emittable() {
+                    self.settings.brush.material = *state;
                 }
+                // picking the selected material again puts the brush away
+                self.settings.brush.state = match self.settings.brush.state {
+                    Some(s) if s.same_kind(state) => None,
+                    _ => Some(*state),
+                };
             }
         }
         // draw coordinates
         let mouse_cell = (
-            (mouse.x as f64 / self.grid.cell_dim.0 as f64
-                - self.grid.dim.0 as f64 / self.grid.cell_dim.0 as f64) as i32,
-            (mouse.y as f64 / self.grid.cell_dim.1 as f64
-                - self.grid.dim.1 as f64 / self.grid.cell_dim.1 as f64) as i32,
+            (mouse.x as f64 / self.grid.cell_dim.0
+                - self.grid.dim.0 / self.grid.cell_dim.0) as i32,
+            (mouse.y as f64 / self.grid.cell_dim.1
+                - self.grid.dim.1 / self.grid.cell_dim.1) as i32,
         );
         if mouse_cell.0 >= 0
             && mouse_cell.0 < self.grid.size.0
@@ -396,6 +749,169 @@ impl Game {
 
         // grid
         self.grid.draw(d);
+
+        self.draw_inspector(d);
+        self.draw_census(d);
+    }
+
+    fn inspector_bounds(&self) -> Rectangle {
+        Rectangle::new(
+            self.grid.dim.0 as f32 + 5.,
+            self.grid.dim.1 as f32 + 5.,
+            190.,
+            272.,
+        )
+    }
+
+    /// Draws the panel with details of the inspected cell.
+    fn draw_inspector(&mut self, d: &mut RaylibDrawHandle) {
+        let pos = match self.inspected {
+            Some(pos) => pos,
+            None => return,
+        };
+        let cell = match self.grid.get_cell(pos.0, pos.1) {
+            Some(cell) => *cell,
+            None => {
+                self.inspected = None;
+                return;
+            }
+        };
+        let bounds = self.inspector_bounds();
+        let title = std::ffi::CString::new(format!("Cell ({}, {})", pos.0, pos.1)).unwrap();
+        if d.gui_window_box(bounds, Some(title.as_c_str())) {
+            self.inspected = None;
+            return;
+        }
+        let fire_level = match cell.state {
+            CellStates::Fire(level) => level.to_string(),
+            CellStates::Burning => cell.heat().to_string(),
+            _ => "-".to_string(),
+        };
+        let mut lines = vec![
+            format!("State: {}", cell.state),
+            format!("Fire level: {fire_level}"),
+            format!("Temp: {}", cell.temp),
+            format!("Hardness: {}", cell.matter().hardness()),
+            format!("Weight: {}", cell.matter().weight()),
+            format!("Charge: {:?}", cell.charge),
+        ];
+        lines.push(format!("Age: {}, counter: {}", cell.data.age, cell.data.counter));
+        if cell.matter().hardness() == Hardness::Liquid as i32 {
+            lines.push(format!("Pressure: {}", self.grid.pressure[pos.0 as usize][pos.1 as usize]));
+        }
+        if let Some(material) = cell.data.material {
+            lines.push(format!("Material: {material}"));
+        }
+        if cell.state == CellStates::Acid {
+            lines.push(format!("Dilution: {}", cell.data.counter));
+        }
+        if let Some(agent) = self.agents.at(pos) {
+            lines.push(format!("Agent: {:?}, hunger {}", agent.behaviour, agent.hunger));
+        }
+        let text_pos = (bounds.x as i32 + 8, bounds.y as i32 + 30);
+        for (idx, line) in lines.iter().enumerate() {
+            d.draw_text(line, text_pos.0, text_pos.1 + idx as i32 * 12, 10, Color::BLACK);
+        }
+        // neighbours in a 3x3 table, the inspected cell in the middle
+        let table_pos = (text_pos.0, text_pos.1 + lines.len() as i32 * 12 + 6);
+        for i in -1..2 {
+            for j in -1..2 {
+                let name = match self.grid.get_cell(pos.0 + i, pos.1 + j) {
+                    Some(other) => other.state.to_string(),
+                    None => "-".to_string(),
+                };
+                let color = if i == 0 && j == 0 { Color::BLUE } else { Color::DARKGRAY };
+                d.draw_text(
+                    &name,
+                    table_pos.0 + (i + 1) * 58,
+                    table_pos.1 + (j + 1) * 12,
+                    10,
+                    color,
+                );
+            }
+        }
+        // result of the last forced step
+        let changes_pos = (table_pos.0, table_pos.1 + 3 * 12 + 6);
+        if self.inspector_changes.is_empty() {
+            d.draw_text("No changes", changes_pos.0, changes_pos.1, 10, Color::GRAY);
+        }
+        for (idx, change) in self.inspector_changes.iter().take(4).enumerate() {
+            d.draw_text(
+                &change.to_string(),
+                changes_pos.0,
+                changes_pos.1 + idx as i32 * 12,
+                10,
+                Color::DARKGRAY,
+            );
+        }
+        if d.gui_button(
+            Rectangle::new(
+                bounds.x + 8.,
+                bounds.y + bounds.height - 30.,
+                bounds.width - 16.,
+                22.,
+            ),
+            Some(cstr::cstr!("Step cell")),
+        ) {
+            self.inspector_changes = self.grid.step_cell(pos.0, pos.1);
+        }
+    }
+
+    fn census_bounds(&self) -> Rectangle {
+        Rectangle::new(
+            (self.grid.dim.0 + self.grid.dim.2) as f32 - 215.,
+            (self.grid.dim.1 + self.grid.dim.3) as f32 - 175.,
+            210.,
+            170.,
+        )
+    }
+
+    /// Draws the panel with the numbers of susceptible, infected, recovered and dead hosts over time.
+    fn draw_census(&mut self, d: &mut RaylibDrawHandle) {
+        if !self.settings.census {
+            return;
+        }
+        let bounds = self.census_bounds();
+        if d.gui_window_box(bounds, Some(cstr::cstr!("Plague"))) {
+            self.settings.census = false;
+            return;
+        }
+        let census = &self.grid.census;
+        let latest = census.back().copied().unwrap_or_default();
+        let text_pos = (bounds.x as i32 + 8, bounds.y as i32 + 30);
+        type Series = (&'static str, fn(&Census) -> i32, Color);
+        let series: [Series; 4] = [
+            ("S", |census| census.susceptible, Color::BLUE),
+            ("I", |census| census.infected, Color::RED),
+            ("R", |census| census.recovered, Color::DARKGREEN),
+            ("D", |census| census.dead, Color::DARKGRAY),
+        ];
+        for (idx, (name, value, color)) in series.iter().enumerate() {
+            d.draw_text(&format!("{name} {}", value(&latest)), text_pos.0 + idx as i32 * 48, text_pos.1, 10, *color);
+        }
+        let graph = Rectangle::new(bounds.x + 8., bounds.y + 46., bounds.width - 16., bounds.height - 84.);
+        d.draw_rectangle_lines(graph.x as i32, graph.y as i32, graph.width as i32, graph.height as i32, Color::LIGHTGRAY);
+        // scaled to the most hosts counted at once, the dead included, so no line leaves the graph
+        let peak = census.iter().map(|c| c.susceptible + c.infected + c.recovered + c.dead).max().unwrap_or(0).max(1);
+        let step = graph.width / (CENSUS_LENGTH - 1) as f32;
+        for (idx, (before, after)) in census.iter().zip(census.iter().skip(1)).enumerate() {
+            let x = graph.x + idx as f32 * step;
+            for (_, value, color) in series.iter() {
+                let y = |census: &Census| graph.y + graph.height - value(census) as f32 / peak as f32 * graph.height;
+                d.draw_line(x as i32, y(before) as i32, (x + step) as i32, y(after) as i32, *color);
+            }
+        }
+        if d.gui_button(
+            Rectangle::new(
+                bounds.x + 8.,
+                bounds.y + bounds.height - 30.,
+                bounds.width - 16.,
+                22.,
+            ),
+            Some(cstr::cstr!("Settings")),
+        ) {
+            self.window = Some("Epidemic".to_string());
+        }
     }
 
     fn backup(&mut self) {
@@ -408,6 +924,7 @@ impl Game {
     fn undo(&mut self) {
         if let Some(grid) = self.backup.pop() {
             self.grid.cells = grid;
+            self.bodies.forget();
         }
     }
 }
@@ -416,6 +933,16 @@ impl Game {
 pub struct Cell {
     pub state: CellStates,
     pub temp: i32,
+    pub charge: Charge,
+    /// Input history of logic components, see `Grid::electric_tick`.
+    pub signal: u32,
+    pub data: CellData,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Cell {
@@ -423,6 +950,58 @@ impl Cell {
         Cell {
             state: CellStates::Air,
             temp: CellStates::Air.temperature(),
+            charge: Charge::Idle,
+            signal: 0,
+            data: CellData::new(0),
+        }
+    }
+
+    /// Colour of the cell, charged conductors are highlighted.
+    pub fn color(&self) -> Color {
+        match self.charge.color() {
+            Some(color) if self.state != CellStates::Power => color,
+            _ if self.state == CellStates::Burning => CellStates::glow(self.heat()),
+            _ => self.state.color(),
+        }
+    }
+
+    /// State whose density, weight and the like the cell has, burning cells keep those of what is burning.
+    pub fn matter(&self) -> CellStates {
+        match self.state {
+            // cells set to burning without going through `Grid::ignite`
+            CellStates::Burning => self.data.material.unwrap_or(CellStates::Wood),
+            state => state,
+        }
+    }
+
+    /// Level of the flames a burning cell gives off, worked out from what is burning.
+    pub fn heat(&self) -> u8 {
+        self.matter().burn().map_or(1, |burn| burn.heat)
+    }
+}
+
+/// Data a cell keeps besides its state. It moves with the cell and starts over when the state changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellData {
+    /// How many times the cell was stepped.
+    pub age: u16,
+    /// Free to use by the material of the cell, no other system may write it.
+    ///
+    /// Burning cells keep their fuel in it, rigid bodies their id and acid how diluted it is.
+    pub counter: i32,
+    /// Material the cell refers to, given out by emitters and clones.
+    pub material: Option<CellStates>,
+    /// Random value picking the shade of the cell, see `CellStates::variation`.
+    pub seed: u8,
+}
+
+impl CellData {
+    pub fn new(seed: u8) -> CellData {
+        CellData {
+            age: 0,
+            counter: 0,
+            material: None,
+            seed,
         }
     }
 }
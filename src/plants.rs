@@ -0,0 +1,196 @@
+use rand::prelude::*;
+
+use crate::*;
+
+/// One in this many steps of a growing tip actually grows, so plants take their time.
+const GROWTH_CHANCE: i32 = 30;
+/// Height range of a freshly sprouted plant.
+const PLANT_HEIGHT: std::ops::Range<u8> = 8..24;
+
+impl Grid {
+    /// Turns a seed resting on sand or soil next to water into a stem.
+    pub fn sprout(&mut self, cell: (i32, i32)) {
+        let down = self.down();
+        let below = match self.get_cell(cell.0 + down.0, cell.1 + down.1) {
+            Some(below) => below.state,
+            None => return,
+        };
+        if !matches!(below, CellStates::Sand | CellStates::WetSand | CellStates::Soil) {
+            return;
+        }
+        if self.rng.gen_range(0..GROWTH_CHANCE) != 0 || !self.is_around(cell.0, cell.1, &CellStates::Water) {
+            return;
+        }
+        let height = self.rng.gen_range(PLANT_HEIGHT);
+        self.set(cell.0, cell.1, CellStates::Stem(height));
+    }
+
+    /// Grows the tip of a stem upwards, drinking water from around its root.
+    ///
+    /// The stem left behind becomes wood, sometimes splitting off a branch, and the top ends in leaves.
+    pub fn grow_stem(&mut self, cell: (i32, i32), height: u8) {
+        if self.rng.gen_range(0..GROWTH_CHANCE) != 0 {
+            return;
+        }
+        let (up, across) = Self::upright(self.down());
+        if height == 0 {
+            self.set(cell.0, cell.1, CellStates::Leaf);
+            for leaf in self.find_all_around(cell.0, cell.1, &CellStates::Air) {
+                // leaves grow around the top and only some hang below it
+                let below = (leaf.0 - cell.0) * up.0 + (leaf.1 - cell.1) * up.1 < 0;
+                if !below || self.rng.gen_range(0..3) == 0 {
+                    self.set(leaf.0, leaf.1, CellStates::Leaf);
+                }
+            }
+            return;
+        }
+        let root = match self.plant_root(cell) {
+            Some(root) => root,
+            None => return,
+        };
+        let water = self.find_all_around(root.0, root.1, &CellStates::Water);
+        if water.is_empty() {
+            return;
+        }
+        let lean = self.rng.gen_range(-1..2) * (self.rng.gen_range(0..4) == 0) as i32;
+        let target = (cell.0 + up.0 + across.0 * lean, cell.1 + up.1 + across.1 * lean);
+        match self.get_cell(target.0, target.1) {
+            Some(other) if other.state == CellStates::Air => (),
+            // no room to grow, finish the plant here
+            _ => {
+                self.set(cell.0, cell.1, CellStates::Stem(0));
+                return;
+            }
+        }
+        let drink = water[self.rng.gen_range(0..water.len())];
+        self.set(drink.0, drink.1, CellStates::Air);
+        self.set(target.0, target.1, CellStates::Stem(height - 1));
+        self.set(cell.0, cell.1, CellStates::Wood);
+        if height > 3 && self.rng.gen_range(0..5) == 0 {
+            let side = self.rng.gen_range(0..2) * 2 - 1;
+            let branch = (cell.0 + up.0 + across.0 * side, cell.1 + up.1 + across.1 * side);
+            if let Some(CellStates::Air) = self.get_cell(branch.0, branch.1).map(|other| other.state) {
+                self.set(branch.0, branch.1, CellStates::Stem(height / 2));
+            }
+        }
+    }
+
+    /// Follows a plant down to its lowest cell, which must be resting on sand or soil.
+    fn plant_root(&self, cell: (i32, i32)) -> Option<(i32, i32)> {
+        let down = self.down();
+        let (_, across) = Self::upright(down);
+        let mut pos = cell;
+        for _ in 0..self.size.0.max(self.size.1) {
+            let next = [down, (down.0 - across.0, down.1 - across.1), (down.0 + across.0, down.1 + across.1)]
+                .iter()
+                .map(|offset| (pos.0 + offset.0, pos.1 + offset.1))
+                .find(|next| matches!(self.get_cell(next.0, next.1).map(|other| other.state), Some(CellStates::Wood | CellStates::Stem(_))));
+            match next {
+                Some(next) => pos = next,
+                None => break,
+            }
+        }
+        match self.get_cell(pos.0 + down.0, pos.1 + down.1)?.state {
+            CellStates::Sand | CellStates::WetSand | CellStates::Soil => Some(pos),
+            _ => None,
+        }
+    }
+
+    /// Grows a vine along the walls it touches.
+    pub fn grow_vine(&mut self, cell: (i32, i32), length: u8) {
+        if length == 0 || self.rng.gen_range(0..GROWTH_CHANCE) != 0 {
+            return;
+        }
+        // prefer climbing up
+        let (up, across) = Self::upright(self.down());
+        let mut targets = [
+            up,
+            (up.0 - across.0, up.1 - across.1),
+            (up.0 + across.0, up.1 + across.1),
+            (-across.0, -across.1),
+            across,
+        ];
+        targets[1..].shuffle(&mut self.rng);
+        for offset in targets {
+            let target = (cell.0 + offset.0, cell.1 + offset.1);
+            if self.get_cell(target.0, target.1).map(|other| other.state) != Some(CellStates::Air) {
+                continue;
+            }
+            if self.find_all_around_where(target.0, target.1, |state| state.climbable()).is_empty() {
+                continue;
+            }
+            self.set(target.0, target.1, CellStates::Vine(length - 1));
+            self.set(cell.0, cell.1, CellStates::Vine(0));
+            return;
+        }
+    }
+
+    /// Up and sideways for plants growing against `down`.
+    fn upright(down: (i32, i32)) -> ((i32, i32), (i32, i32)) {
+        ((-down.0, -down.1), (down.1, -down.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    /// Plants in the garden scene after `generations` generations.
+    fn garden(generations: i32) -> Grid {
+        let mut grid = Grid::new((96, 80));
+        scenes::garden(&mut grid);
+        for _ in 0..generations * grid.generation() {
+            grid.step();
+        }
+        grid
+    }
+
+    fn count(grid: &Grid, f: impl Fn(&CellStates) -> bool) -> usize {
+        grid.cells.iter().flatten().filter(|cell| f(&cell.state)).count()
+    }
+
+    #[test]
+    fn growth_is_deterministic() {
+        let first = garden(150);
+        let second = garden(150);
+        for (x, (a, b)) in first.cells.iter().zip(second.cells.iter()).enumerate() {
+            for (y, (a, b)) in a.iter().zip(b.iter()).enumerate() {
+                assert_eq!((a.state, a.data), (b.state, b.data), "cell ({x}, {y}) differs");
+            }
+        }
+    }
+
+    #[test]
+    fn garden_grows() {
+        let grid = garden(150);
+        assert!(count(&grid, |state| matches!(state, CellStates::Stem(_) | CellStates::Leaf)) > 0);
+        assert!(count(&grid, |state| *state == CellStates::Wood) > 0);
+        assert!(count(&grid, |state| matches!(state, CellStates::Vine(_))) > 2);
+        assert_eq!(count(&grid, |state| *state == CellStates::Seed), 0);
+    }
+
+    #[test]
+    fn plants_grow_against_gravity() {
+        let mut grid = Grid::new((60, 60));
+        grid.seed(3);
+        grid.gravity = Some(Dir::Right);
+        for x in 50..60 {
+            for y in 0..60 {
+                grid.set(x, y, CellStates::Soil);
+            }
+        }
+        for y in (5..50).step_by(12) {
+            for i in 0..4 {
+                grid.set(48, y + i, CellStates::Water);
+                grid.set(49, y + i, CellStates::Water);
+            }
+            grid.set(49, y + 5, CellStates::Seed);
+        }
+        for _ in 0..200 * grid.generation() {
+            grid.step();
+        }
+        // grown out to the left, away from the soil
+        let grown = grid.cells[..45].iter().flatten().filter(|cell| matches!(cell.state, CellStates::Wood | CellStates::Leaf)).count();
+        assert!(grown > 0);
+    }
+}
@@ -0,0 +1,101 @@
+use crate::*;
+
+/// Most cells moved from the high surface of a liquid to the low one every generation.
+const MAX_TRANSFERS: usize = 4;
+/// Pressure that pushes a liquid flowing out sideways one cell further.
+const JET_PRESSURE: i32 = 6;
+
+impl Grid {
+    /// Works out the pressure of every liquid and levels out connected bodies of liquid.
+    ///
+    /// The pressure of a cell is its depth under the highest surface of its body, kept in `Grid::pressure`.
+    /// Liquid from the highest surfaces moves over the lowest ones, so that the levels in connected vessels even out.
+    pub fn pressure_tick(&mut self) {
+        let mut pressure = vec![vec![0; self.size.1 as usize]; self.size.0 as usize];
+        let down = match self.gravity {
+            Some(dir) => dir.offset(),
+            None => {
+                self.pressure = pressure;
+                return;
+            }
+        };
+        // height against gravity
+        let level = |pos: (i32, i32)| -(pos.0 * down.0 + pos.1 * down.1);
+        let mut visited = vec![vec![false; self.size.1 as usize]; self.size.0 as usize];
+        for x in 0..self.size.0 {
+            for y in 0..self.size.1 {
+                let state = self.cells[x as usize][y as usize].state;
+                if visited[x as usize][y as usize] || state.hardness() != Hardness::Liquid as i32 {
+                    continue;
+                }
+                // flood the body of the same liquid
+                let mut body = Vec::new();
+                let mut stack = vec![(x, y)];
+                visited[x as usize][y as usize] = true;
+                while let Some(pos) = stack.pop() {
+                    body.push(pos);
+                    for offset in [(0, -1), (1, 0), (0, 1), (-1, 0)] {
+                        let next = (pos.0 + offset.0, pos.1 + offset.1);
+                        if self.bounds(next.0, next.1)
+                            && !visited[next.0 as usize][next.1 as usize]
+                            && self.cells[next.0 as usize][next.1 as usize].state == state
+                        {
+                            visited[next.0 as usize][next.1 as usize] = true;
+                            stack.push(next);
+                        }
+                    }
+                }
+                // resting cells with open space above them
+                let mut surface: Vec<(i32, i32)> = body
+                    .iter()
+                    .copied()
+                    .filter(|pos| self.free(pos.0 - down.0, pos.1 - down.1) && !self.free(pos.0 + down.0, pos.1 + down.1))
+                    .collect();
+                let top = match surface.iter().map(|pos| level(*pos)).max() {
+                    Some(top) => top,
+                    None => continue,
+                };
+                for pos in body.iter() {
+                    pressure[pos.0 as usize][pos.1 as usize] = top - level(*pos);
+                }
+                surface.sort_by_key(|pos| level(*pos));
+                for idx in 0..MAX_TRANSFERS.min(surface.len() / 2) {
+                    let (low, high) = (surface[idx], surface[surface.len() - 1 - idx]);
+                    if level(high) - level(low) < 2 {
+                        break;
+                    }
+                    // the whole cell moves, so that its data goes with it
+                    self.swap(high.0, high.1, (low.0 - down.0, low.1 - down.1));
+                }
+            }
+        }
+        self.pressure = pressure;
+    }
+
+    /// Pushes a liquid that just flowed out sideways further along `dir`, the more the higher the pressure it had.
+    pub fn jet(&mut self, from: (i32, i32), dir: (i32, i32), pressure: i32) {
+        let cell = match self.get_cell(from.0, from.1) {
+            Some(cell) => *cell,
+            None => return,
+        };
+        let mut pos = from;
+        for _ in 0..(pressure / JET_PRESSURE).min(3) {
+            let next = (pos.0 + dir.0, pos.1 + dir.1);
+            match self.get_cell(next.0, next.1) {
+                Some(other) if other.matter().hardness() > cell.matter().hardness() => {
+                    self.swap(pos.0, pos.1, next);
+                    pos = next;
+                }
+                _ => break,
+            }
+        }
+    }
+
+    /// Whether a liquid could move into the cell.
+    fn free(&self, x: i32, y: i32) -> bool {
+        match self.get_cell(x, y) {
+            Some(cell) => cell.state.hardness() > Hardness::Liquid as i32,
+            None => false,
+        }
+    }
+}
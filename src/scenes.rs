@@ -0,0 +1,121 @@
+use rand::prelude::*;
+
+use crate::*;
+
+/// Name, description and builder of a scene.
+pub type Scene = (&'static str, &'static str, fn(&mut Grid));
+
+/// Every scene, in the order of the scenes window.
+pub fn all() -> Vec<Scene> {
+    vec![
+        ("Clock", "Lamps blinking on a circuit loop", clock),
+        ("Gates", "AND, OR and NOT with switches", gates),
+        ("Garden", "Seeds on wet soil and vines on a wall", garden),
+        ("Epidemic", "Hosts in a room and the first cases of plague", epidemic),
+    ]
+}
+
+fn clear(grid: &mut Grid) {
+    for column in grid.cells.iter_mut() {
+        for cell in column.iter_mut() {
+            *cell = Cell::new();
+        }
+    }
+    grid.gravity = Some(Dir::Down);
+    grid.gravity_strength = FULL_GRAVITY;
+    grid.clear_census();
+}
+
+fn line(grid: &mut Grid, from: (i32, i32), to: (i32, i32), state: CellStates) {
+    for x in from.0.min(to.0)..=from.0.max(to.0) {
+        for y in from.1.min(to.1)..=from.1.max(to.1) {
+            grid.set(x, y, state);
+        }
+    }
+}
+
+/// A loop of 12 wires with a single electron, so it pulses every 12 ticks.
+///
+/// The top lamp blinks with every pulse, the bottom one is behind a NOT gate and goes dark instead.
+pub fn clock(grid: &mut Grid) {
+    clear(grid);
+    let origin = (40, 40);
+    // corners are left out, the charge jumps them diagonally
+    line(grid, (origin.0 + 1, origin.1), (origin.0 + 4, origin.1), CellStates::Metal);
+    line(grid, (origin.0 + 1, origin.1 + 3), (origin.0 + 4, origin.1 + 3), CellStates::Metal);
+    line(grid, (origin.0, origin.1 + 1), (origin.0, origin.1 + 2), CellStates::Metal);
+    line(grid, (origin.0 + 5, origin.1 + 1), (origin.0 + 5, origin.1 + 2), CellStates::Metal);
+    grid.cells[origin.0 as usize + 1][origin.1 as usize].charge = Charge::Head;
+    grid.cells[origin.0 as usize][origin.1 as usize + 1].charge = Charge::Tail;
+
+    grid.set(origin.0 + 2, origin.1 - 1, CellStates::Diode(Dir::Up));
+    line(grid, (origin.0 + 2, origin.1 - 2), (origin.0 + 2, origin.1 - 4), CellStates::Metal);
+    grid.set(origin.0 + 2, origin.1 - 5, CellStates::Lamp(false));
+
+    grid.set(origin.0 + 2, origin.1 + 4, CellStates::Not(Dir::Down));
+    line(grid, (origin.0 + 2, origin.1 + 5), (origin.0 + 2, origin.1 + 7), CellStates::Metal);
+    grid.set(origin.0 + 2, origin.1 + 8, CellStates::Lamp(false));
+}
+
+/// Logic gates fed by powered switches, each lighting a lamp.
+pub fn gates(grid: &mut Grid) {
+    clear(grid);
+    for (row, gate) in [(30, CellStates::And(Dir::Right)), (50, CellStates::Or(Dir::Right))] {
+        // two inputs coming in diagonally from behind
+        for input in [row - 1, row + 1] {
+            grid.set(40, input, CellStates::Power);
+            grid.set(41, input, CellStates::Switch(false));
+            line(grid, (42, input), (49, input), CellStates::Metal);
+        }
+        grid.set(50, row, gate);
+        line(grid, (51, row), (55, row), CellStates::Metal);
+        grid.set(56, row, CellStates::Lamp(false));
+    }
+    let row = 70;
+    grid.set(40, row, CellStates::Power);
+    grid.set(41, row, CellStates::Switch(false));
+    line(grid, (42, row), (49, row), CellStates::Metal);
+    grid.set(50, row, CellStates::Not(Dir::Right));
+    line(grid, (51, row), (55, row), CellStates::Metal);
+    grid.set(56, row, CellStates::Lamp(false));
+}
+
+/// Soil with puddles and seeds, next to a wall with vines at its foot.
+///
+/// The random generator is seeded, so the same number of steps grows the same garden, unless disturbed.
+pub fn garden(grid: &mut Grid) {
+    clear(grid);
+    grid.seed(0x6A7DE);
+    let floor = grid.size.1 - 10;
+    line(grid, (0, floor), (grid.size.0 - 1, grid.size.1 - 1), CellStates::Soil);
+    for x in (10..grid.size.0 - 40).step_by(12) {
+        line(grid, (x, floor - 2), (x + 3, floor - 1), CellStates::Water);
+        grid.set(x + 5, floor - 1, CellStates::Seed);
+    }
+    let wall = grid.size.0 - 20;
+    line(grid, (wall, floor - 60), (wall + 2, floor - 1), CellStates::Wall);
+    grid.set(wall - 1, floor - 1, CellStates::Vine(VINE_LENGTH));
+    grid.set(wall + 3, floor - 1, CellStates::Vine(VINE_LENGTH));
+}
+
+/// A closed room a third full of hosts, with a few infected ones in the middle.
+///
+/// Press P to watch the numbers of susceptible, infected and recovered hosts.
+pub fn epidemic(grid: &mut Grid) {
+    clear(grid);
+    grid.seed(0xE91D);
+    let (right, bottom) = (grid.size.0 - 11, grid.size.1 - 11);
+    line(grid, (10, 10), (right, 10), CellStates::Wall);
+    line(grid, (10, bottom), (right, bottom), CellStates::Wall);
+    line(grid, (10, 10), (10, bottom), CellStates::Wall);
+    line(grid, (right, 10), (right, bottom), CellStates::Wall);
+    for x in 11..right {
+        for y in 11..bottom {
+            if grid.rng.gen_range(0..3) == 0 {
+                grid.set(x, y, CellStates::Host);
+            }
+        }
+    }
+    let centre = (grid.size.0 / 2, grid.size.1 / 2);
+    line(grid, (centre.0 - 1, centre.1 - 1), (centre.0 + 1, centre.1 + 1), CellStates::Plague);
+}
@@ -0,0 +1,109 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::*;
+
+impl Grid {
+    /// Lets solids that lost their support fall and breaks off overhangs reaching out too far.
+    ///
+    /// Connected structural solids form one cluster. Reach spreads from the cells that rest on the floor,
+    /// on other solids or next to a barrier, going against gravity costs nothing and every other step costs one.
+    /// Cells with more reach than their strength break off, so whole unsupported clusters fall as one group.
+    pub fn structure_tick(&mut self) {
+        // weak gravity lets them hang for a while
+        let gravity = self.gravity;
+        let down = match gravity {
+            Some(dir) if self.pulled() => dir.offset(),
+            _ => return,
+        };
+        let mut cluster_of = vec![vec![usize::MAX; self.size.1 as usize]; self.size.0 as usize];
+        let mut clusters: Vec<Vec<(i32, i32)>> = Vec::new();
+        for x in 0..self.size.0 {
+            for y in 0..self.size.1 {
+                if cluster_of[x as usize][y as usize] != usize::MAX || self.cells[x as usize][y as usize].state.strength().is_none() {
+                    continue;
+                }
+                let id = clusters.len();
+                let mut cluster = Vec::new();
+                let mut stack = vec![(x, y)];
+                cluster_of[x as usize][y as usize] = id;
+                while let Some(pos) = stack.pop() {
+                    cluster.push(pos);
+                    for next in Self::sides(pos) {
+                        let structural = self.get_cell(next.0, next.1).is_some_and(|cell| cell.state.strength().is_some());
+                        if structural && cluster_of[next.0 as usize][next.1 as usize] == usize::MAX {
+                            cluster_of[next.0 as usize][next.1 as usize] = id;
+                            stack.push(next);
+                        }
+                    }
+                }
+                clusters.push(cluster);
+            }
+        }
+        let mut reach = vec![vec![i32::MAX; self.size.1 as usize]; self.size.0 as usize];
+        for cluster in clusters {
+            let mut queue = VecDeque::new();
+            for pos in cluster.iter() {
+                if self.supported(*pos, down) {
+                    reach[pos.0 as usize][pos.1 as usize] = 0;
+                    queue.push_back(*pos);
+                }
+            }
+            while let Some(pos) = queue.pop_front() {
+                let current = reach[pos.0 as usize][pos.1 as usize];
+                for next in Self::sides(pos) {
+                    if !self.bounds(next.0, next.1) || cluster_of[next.0 as usize][next.1 as usize] != cluster_of[pos.0 as usize][pos.1 as usize] {
+                        continue;
+                    }
+                    let cost = if next == (pos.0 - down.0, pos.1 - down.1) { 0 } else { 1 };
+                    if current + cost < reach[next.0 as usize][next.1 as usize] {
+                        reach[next.0 as usize][next.1 as usize] = current + cost;
+                        match cost {
+                            0 => queue.push_front(next),
+                            _ => queue.push_back(next),
+                        }
+                    }
+                }
+            }
+            let broken: Vec<(i32, i32)> = cluster
+                .into_iter()
+                .filter(|pos| {
+                    let strength = self.cells[pos.0 as usize][pos.1 as usize].state.strength().unwrap_or(0);
+                    reach[pos.0 as usize][pos.1 as usize] > strength
+                })
+                .collect();
+            self.drop_group(broken, down);
+        }
+    }
+
+    /// Moves the cells one step down together, pushing aside what they fall into. Nothing moves when any of them is blocked.
+    fn drop_group(&mut self, mut group: Vec<(i32, i32)>, down: (i32, i32)) {
+        let members: HashSet<(i32, i32)> = group.iter().copied().collect();
+        let blocked = group.iter().any(|pos| {
+            let target = (pos.0 + down.0, pos.1 + down.1);
+            !members.contains(&target) && self.get_cell(target.0, target.1).is_none_or(|cell| cell.state.hardness() <= Hardness::Solid as i32)
+        });
+        if blocked {
+            return;
+        }
+        // the lowest cells go first and make room for the ones above
+        group.sort_by_key(|pos| -(pos.0 * down.0 + pos.1 * down.1));
+        for pos in group {
+            self.swap(pos.0, pos.1, (pos.0 + down.0, pos.1 + down.1));
+        }
+    }
+
+    /// Whether the structural cell stands on the floor or on another solid, or is held by a barrier.
+    fn supported(&self, pos: (i32, i32), down: (i32, i32)) -> bool {
+        let barrier = Self::sides(pos)
+            .iter()
+            .any(|side| matches!(self.get_cell(side.0, side.1), Some(cell) if cell.state == CellStates::Barrier));
+        barrier || match self.get_cell(pos.0 + down.0, pos.1 + down.1) {
+            Some(cell) => cell.state.hardness() <= Hardness::Solid as i32 && cell.state.strength().is_none(),
+            None => true,
+        }
+    }
+
+    fn sides(pos: (i32, i32)) -> [(i32, i32); 4] {
+        [(pos.0, pos.1 - 1), (pos.0 + 1, pos.1), (pos.0, pos.1 + 1), (pos.0 - 1, pos.1)]
+    }
+}
@@ -3,6 +3,8 @@ use std::collections::HashMap;
 use raylib::prelude::*;
 
 use crate::grid::*;
+use crate::cells::*;
+use crate::scenes;
 
 use cstr::cstr;
 
@@ -10,25 +12,240 @@ pub trait Window {
     fn draw(&mut self, grid: &mut Grid, d: &mut RaylibDrawHandle) -> bool;
 }
 
+/// Height of the title bar drawn by `gui_window_box`.
+const TITLE_HEIGHT: i32 = 24;
+
+/// Bounds of a window covering most of the screen.
+fn window_bounds(d: &RaylibDrawHandle) -> Rectangle {
+    let screen = (d.get_screen_width() as f32, d.get_screen_height() as f32);
+    Rectangle::new(screen.0 * 0.1, screen.1 * 0.1, screen.0 * 0.8, screen.1 * 0.8)
+}
+
+/// Places lines of text from top to bottom, continuing in the next column when the window runs out of space.
+struct Layout {
+    x: i32,
+    y: i32,
+    top: i32,
+    bottom: i32,
+    column_width: i32,
+    font: i32,
+    /// Lines go on past the bottom in one column and only the ones inside the window are drawn.
+    scrolling: bool,
+}
+
+impl Layout {
+    fn new(bounds: Rectangle, columns: i32) -> Layout {
+        // text grows with the window
+        let font = ((bounds.height / 30.).min(bounds.width / 50.) as i32).clamp(10, 20);
+        let top = bounds.y as i32 + TITLE_HEIGHT + font / 2;
+        Layout {
+            x: bounds.x as i32 + font,
+            y: top,
+            top,
+            bottom: (bounds.y + bounds.height) as i32 - font / 2,
+            column_width: (bounds.width as i32 - font * 2) / columns,
+            font,
+            scrolling: false,
+        }
+    }
+
+    /// Turns the layout into one scrolling column, moved up by `offset` pixels.
+    fn scrolled(mut self, offset: i32) -> Layout {
+        self.y -= offset;
+        self.scrolling = true;
+        self
+    }
+
+    /// Pixels the lines placed so far reach past the bottom of the window, negative when they end above it.
+    fn overflow(&self) -> i32 {
+        self.y - self.bottom
+    }
+
+    /// Whether a line at `pos` is inside the window.
+    fn visible(&self, pos: (i32, i32), font: i32) -> bool {
+        pos.1 >= self.top && pos.1 + font <= self.bottom
+    }
+
+    /// Returns the position of the next line of the given font size.
+    fn line(&mut self, font: i32) -> (i32, i32) {
+        if !self.scrolling && self.y + font > self.bottom {
+            self.x += self.column_width;
+            self.y = self.top;
+        }
+        let pos = (self.x, self.y);
+        self.y += font + font / 2;
+        pos
+    }
+
+    fn text(&mut self, d: &mut RaylibDrawHandle, text: &str, font: i32, color: Color) {
+        let pos = self.line(font);
+        if self.visible(pos, font) {
+            d.draw_text(text, pos.0, pos.1, font, color);
+        }
+    }
+
+    fn gap(&mut self) {
+        self.y += self.font / 2;
+    }
+}
+
 pub struct About {
 
 }
 
 impl Window for About {
-    fn draw(&mut self, grid: &mut Grid, d: &mut RaylibDrawHandle) -> bool {
-        let result = d.gui_window_box(Rectangle::new(0.0, 0.0, 200.0, 200.0), Some(cstr!("About")));
+    fn draw(&mut self, _grid: &mut Grid, d: &mut RaylibDrawHandle) -> bool {
+        let bounds = window_bounds(d);
+        let result = d.gui_window_box(bounds, Some(cstr!("About")));
+
+        let mut layout = Layout::new(bounds, 1);
+        let font = layout.font;
+        layout.text(d, "AutomaTomas", font * 2, Color::BLACK);
+        layout.text(d, &format!("Version {}", env!("CARGO_PKG_VERSION")), font, Color::DARKGRAY);
+        layout.gap();
+        layout.text(d, "A cellular automaton game with pre-defined rules,", font, Color::BLACK);
+        layout.text(d, "the successor of the AutomaTom engine.", font, Color::BLACK);
+        layout.gap();
+        layout.text(d, "Created by it-2001 and contributors.", font, Color::BLACK);
+        layout.text(d, "Built with raylib-rs and raygui.", font, Color::BLACK);
+        layout.gap();
+        layout.text(d, "github.com/it-2001/automatomas", font, Color::DARKBLUE);
 
         !result
     }
 }
 
 pub struct Help {
-
+    /// Pixels the text is scrolled by, the list of materials is longer than most windows are high.
+    scroll: i32,
 }
 
 impl Window for Help {
+    fn draw(&mut self, _grid: &mut Grid, d: &mut RaylibDrawHandle) -> bool {
+        let bounds = window_bounds(d);
+        let result = d.gui_window_box(bounds, Some(cstr!("Help")));
+
+        let font = Layout::new(bounds, 1).font;
+        self.scroll = (self.scroll - d.get_mouse_wheel_move() as i32 * font * 3).max(0);
+        let mut layout = Layout::new(bounds, 1).scrolled(self.scroll);
+        layout.text(d, "Controls", font + 4, Color::BLACK);
+        for line in [
+            "Left click - place the selected cell",
+            "Right click - remove cells",
+            "Left click with no cell selected - inspect",
+            "Left panel - settings, saves and stepping",
+            "Right panel - cell types",
+            "R - rotate logic components and fans",
+            "G - rotate gravity, then zero gravity",
+            "C - let unsupported solids collapse",
+            "P - graph of the plague, with its settings",
+            "Click a switch with no cell selected - flip it",
+            "Emitters give out the last material picked",
+            "Mouse wheel - scroll this help",
+        ] {
+            layout.text(d, line, font, Color::DARKGRAY);
+        }
+        layout.gap();
+        layout.text(d, "Materials", font + 4, Color::BLACK);
+        for state in CellStates::list() {
+            let pos = layout.line(font);
+            if !layout.visible(pos, font) {
+                continue;
+            }
+            d.draw_rectangle(pos.0, pos.1, font, font, state.color());
+            d.draw_rectangle_lines(pos.0, pos.1, font, font, Color::BLACK);
+            d.draw_text(
+                &format!("{state} - {}", state.description()),
+                pos.0 + font + font / 2,
+                pos.1,
+                font,
+                Color::DARKGRAY,
+            );
+        }
+        // no scrolling past the end of the list
+        self.scroll = (self.scroll + layout.overflow().min(0)).max(0);
+
+        !result
+    }
+}
+
+pub struct Scenes {
+
+}
+
+impl Window for Scenes {
     fn draw(&mut self, grid: &mut Grid, d: &mut RaylibDrawHandle) -> bool {
-        let result = d.gui_window_box(Rectangle::new(0.0, 0.0, 200.0, 200.0), Some(cstr!("Help")));
+        let bounds = window_bounds(d);
+        let result = d.gui_window_box(bounds, Some(cstr!("Scenes")));
+
+        let mut layout = Layout::new(bounds, 2);
+        let font = layout.font;
+        layout.text(d, "Replaces the grid with a prepared scene", font, Color::DARKGRAY);
+        layout.gap();
+        for (name, description, build) in scenes::all() {
+            let pos = layout.line(font * 2);
+            let name = std::ffi::CString::new(name).unwrap();
+            if d.gui_button(
+                Rectangle::new(pos.0 as f32, pos.1 as f32, (font * 8) as f32, (font * 2) as f32),
+                Some(name.as_c_str()),
+            ) {
+                build(grid);
+                return false;
+            }
+            d.draw_text(description, pos.0 + font * 9, pos.1 + font / 2, font, Color::DARKGRAY);
+        }
+
+        !result
+    }
+}
+
+pub struct Epidemic {
+
+}
+
+impl Window for Epidemic {
+    fn draw(&mut self, grid: &mut Grid, d: &mut RaylibDrawHandle) -> bool {
+        let bounds = window_bounds(d);
+        let result = d.gui_window_box(bounds, Some(cstr!("Epidemic")));
+
+        let mut layout = Layout::new(bounds, 1);
+        let font = layout.font;
+        layout.text(d, "How the plague spreads through hosts, times are in steps of a cell", font, Color::DARKGRAY);
+        layout.gap();
+        let contagion = &mut grid.contagion;
+        for (name, value, max) in [
+            ("Infection %", &mut contagion.infection, 100),
+            ("Incubation", &mut contagion.incubation, 500),
+            ("Duration", &mut contagion.duration, 500),
+            ("Mortality %", &mut contagion.mortality, 100),
+            ("Immunity", &mut contagion.immunity, 3000),
+        ] {
+            let pos = layout.line(font * 2);
+            d.draw_text(name, pos.0, pos.1 + font / 2, font, Color::BLACK);
+            let shown = std::ffi::CString::new(value.to_string()).unwrap();
+            *value = d.gui_slider_bar(
+                Rectangle::new((pos.0 + font * 8) as f32, pos.1 as f32, (font * 16) as f32, (font * 2) as f32),
+                None,
+                Some(shown.as_c_str()),
+                *value as f32,
+                0.,
+                max as f32,
+            ) as i32;
+        }
+        layout.gap();
+        let pos = layout.line(font * 2);
+        if d.gui_button(
+            Rectangle::new(pos.0 as f32, pos.1 as f32, (font * 8) as f32, (font * 2) as f32),
+            Some(cstr!("Defaults")),
+        ) {
+            grid.contagion = crate::epidemic::Contagion::default();
+        }
+        if d.gui_button(
+            Rectangle::new((pos.0 + font * 9) as f32, pos.1 as f32, (font * 8) as f32, (font * 2) as f32),
+            Some(cstr!("Clear graph")),
+        ) {
+            grid.clear_census();
+        }
 
         !result
     }
@@ -38,7 +255,9 @@ pub fn get_all_windows() -> HashMap<String, Box<dyn Window>> {
     let mut windows: HashMap<String, Box<dyn Window>> = HashMap::new();
 
     windows.insert("About".to_string(), Box::new(About{}));
-    windows.insert("Help".to_string(), Box::new(Help{}));
+    windows.insert("Help".to_string(), Box::new(Help { scroll: 0 }));
+    windows.insert("Scenes".to_string(), Box::new(Scenes{}));
+    windows.insert("Epidemic".to_string(), Box::new(Epidemic{}));
 
     windows
 }
\ No newline at end of file